@@ -1,21 +1,29 @@
 use crate::Answer;
+use hashbrown::HashMap;
+use priority_queue::PriorityQueue;
+use std::{cmp::Reverse, fmt::Display, hash::Hash, str::FromStr};
 
 // tag::setup[]
-struct Position {
-	h: i32,
-	v: i32,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+	pub h: i32,
+	pub v: i32,
 }
 
 impl Position {
-	fn get_ans(&self) -> i32 {
-		self.h * self.v
+	/// `None` if the product overflows
+	pub fn get_ans(&self) -> Option<i32> {
+		self.h.checked_mul(self.v)
 	}
 }
 
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
 	Forward,
+	Back,
 	Up,
 	Down,
+	Turn,
 }
 
 impl Direction {
@@ -23,16 +31,20 @@ impl Direction {
 		use Direction::*;
 		Some(match s {
 			"forward" => Forward,
+			"back" => Back,
 			"up" => Up,
 			"down" => Down,
+			"turn" => Turn,
 			_ => return None,
 		})
 	}
 }
 
-struct Step {
-	direction: Direction,
-	dist: i32,
+/// For `Turn`, `dist` is the number of about-faces to make
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+	pub direction: Direction,
+	pub dist: i32,
 }
 
 fn read_input(s: &str) -> Option<Vec<Step>> {
@@ -49,7 +61,14 @@ fn read_input(s: &str) -> Option<Vec<Step>> {
 
 fn ans_for_input(input: &str) -> Answer<i32, i32> {
 	let directions = read_input(input).unwrap();
-	(2, (pt1(directions.iter()), pt2(directions.iter()))).into()
+	(
+		2,
+		(
+			pt1(directions.iter()).unwrap(),
+			pt2(directions.iter()).unwrap(),
+		),
+	)
+		.into()
 }
 
 pub fn ans() -> Answer<i32, i32> {
@@ -57,47 +76,568 @@ pub fn ans() -> Answer<i32, i32> {
 }
 // end::setup[]
 
+// tag::steering[]
+/// The semantics of a pilot: how each `Step` changes the submarine's state. `Clone` lets
+/// `trace` record the state after every step
+pub trait SteeringModel: Clone {
+	/// Returns `None`, leaving the state unchanged, if the step would take any part of
+	/// the state out of range of an `i32`
+	fn apply(&mut self, step: Step) -> Option<()>;
+
+	fn position(&self) -> Position;
+
+	fn get_ans(&self) -> Option<i32> {
+		self.position().get_ans()
+	}
+}
+
+/// `step` is the index of the first step that overflowed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow {
+	pub step: usize,
+}
+
+/// Runs `steps` starting from `model` and returns the final state
+///
+/// # Errors
+///
+/// Stops at the first step that overflows
+pub fn run<M: SteeringModel, T: std::borrow::Borrow<Step>>(
+	mut model: M,
+	steps: impl Iterator<Item = T>,
+) -> Result<M, Overflow> {
+	for (i, step) in steps.enumerate() {
+		model.apply(*step.borrow()).ok_or(Overflow { step: i })?;
+	}
+	Ok(model)
+}
+
+/// Runs `steps` starting from `model` and returns the state after each step
+///
+/// # Errors
+///
+/// Stops at the first step that overflows
+pub fn trace<M: SteeringModel, T: std::borrow::Borrow<Step>>(
+	mut model: M,
+	steps: impl Iterator<Item = T>,
+) -> Result<Vec<M>, Overflow> {
+	steps
+		.enumerate()
+		.map(|(i, step)| {
+			model.apply(*step.borrow()).ok_or(Overflow { step: i })?;
+			Ok(model.clone())
+		})
+		.collect()
+}
+
+fn heading_after_turn(heading: i32, n_turns: i32) -> i32 {
+	if n_turns % 2 == 0 {
+		heading
+	} else {
+		-heading
+	}
+}
+// end::steering[]
+
 // tag::pt1[]
-fn pt1<T: std::ops::Deref<Target = Step>>(steps: impl Iterator<Item = T>) -> i32 {
-	use Direction::*;
-	let mut h = 0;
-	let mut v = 0;
-	for step in steps {
-		let Step { direction, dist } = &*step;
+/// Up and down move the submarine directly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Simple {
+	pub pos: Position,
+	pub heading: i32,
+}
+
+impl Default for Simple {
+	fn default() -> Self {
+		Self {
+			pos: Position { h: 0, v: 0 },
+			heading: 1,
+		}
+	}
+}
+
+impl SteeringModel for Simple {
+	fn apply(&mut self, Step { direction, dist }: Step) -> Option<()> {
+		use Direction::*;
+		let mut next = *self;
 		match direction {
-			Forward => h += dist,
-			Up => v -= dist,
-			Down => v += dist,
-		};
+			Forward => next.pos.h = next.pos.h.checked_add(next.heading.checked_mul(dist)?)?,
+			Back => next.pos.h = next.pos.h.checked_sub(next.heading.checked_mul(dist)?)?,
+			Up => next.pos.v = next.pos.v.checked_sub(dist)?,
+			Down => next.pos.v = next.pos.v.checked_add(dist)?,
+			Turn => next.heading = heading_after_turn(next.heading, dist),
+		}
+		*self = next;
+		Some(())
 	}
 
-	Position { h, v }.get_ans()
+	fn position(&self) -> Position {
+		self.pos
+	}
+}
+
+fn pt1<T: std::borrow::Borrow<Step>>(steps: impl Iterator<Item = T>) -> Option<i32> {
+	run(Simple::default(), steps).ok()?.get_ans()
 }
 // end::pt1[]
 
 // tag::pt2[]
-fn pt2<T: std::borrow::Borrow<Step>>(steps: impl Iterator<Item = T>) -> i32 {
-	use Direction::*;
-	let mut h = 0;
-	let mut v = 0;
-	let mut aim = 0;
-	for step in steps {
-		let Step { direction, dist } = step.borrow();
-		let dist = *dist;
+/// Up and down change the aim, and moving changes the depth in proportion to the aim
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Aimed {
+	pub pos: Position,
+	pub aim: i32,
+	pub heading: i32,
+}
+
+impl Default for Aimed {
+	fn default() -> Self {
+		Self {
+			pos: Position { h: 0, v: 0 },
+			aim: 0,
+			heading: 1,
+		}
+	}
+}
+
+impl SteeringModel for Aimed {
+	fn apply(&mut self, Step { direction, dist }: Step) -> Option<()> {
+		use Direction::*;
+		let mut next = *self;
 		match direction {
 			Forward => {
-				h += dist;
-				v += aim * dist;
+				next.pos.h = next.pos.h.checked_add(next.heading.checked_mul(dist)?)?;
+				next.pos.v = next.pos.v.checked_add(next.aim.checked_mul(dist)?)?;
 			}
-			Up => aim -= dist,
-			Down => aim += dist,
-		};
+			Back => {
+				next.pos.h = next.pos.h.checked_sub(next.heading.checked_mul(dist)?)?;
+				next.pos.v = next.pos.v.checked_sub(next.aim.checked_mul(dist)?)?;
+			}
+			Up => next.aim = next.aim.checked_sub(dist)?,
+			Down => next.aim = next.aim.checked_add(dist)?,
+			Turn => next.heading = heading_after_turn(next.heading, dist),
+		}
+		*self = next;
+		Some(())
 	}
 
-	Position { h, v }.get_ans()
+	fn position(&self) -> Position {
+		self.pos
+	}
+}
+
+fn pt2<T: std::borrow::Borrow<Step>>(steps: impl Iterator<Item = T>) -> Option<i32> {
+	run(Aimed::default(), steps).ok()?.get_ans()
 }
 // end::pt2[]
 
+// tag::models[]
+/// The aimed model in a current that pushes the submarine sideways by `drift` for
+/// every unit it moves forward or back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Drifting {
+	pub aimed: Aimed,
+	pub drift: i32,
+	pub lateral: i32,
+}
+
+impl Drifting {
+	pub fn new(drift: i32) -> Self {
+		Self {
+			aimed: Aimed::default(),
+			drift,
+			lateral: 0,
+		}
+	}
+}
+
+impl SteeringModel for Drifting {
+	fn apply(&mut self, step: Step) -> Option<()> {
+		let mut lateral = self.lateral;
+		if matches!(step.direction, Direction::Forward | Direction::Back) {
+			lateral = lateral.checked_add(self.drift.checked_mul(step.dist.checked_abs()?)?)?;
+		}
+		self.aimed.apply(step)?;
+		self.lateral = lateral;
+		Some(())
+	}
+
+	fn position(&self) -> Position {
+		self.aimed.position()
+	}
+}
+
+/// Wraps another model, capping the distance of any one forward or back step at
+/// `max_dist`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpeedLimited<M> {
+	pub inner: M,
+	max_dist: i32,
+}
+
+impl<M> SpeedLimited<M> {
+	/// Panics if `max_dist` is negative
+	pub fn new(inner: M, max_dist: i32) -> Self {
+		assert!(
+			max_dist >= 0,
+			"speed limit must not be negative, got {max_dist}"
+		);
+		Self { inner, max_dist }
+	}
+
+	pub fn max_dist(&self) -> i32 {
+		self.max_dist
+	}
+}
+
+impl<M: SteeringModel> SteeringModel for SpeedLimited<M> {
+	fn apply(&mut self, mut step: Step) -> Option<()> {
+		if matches!(step.direction, Direction::Forward | Direction::Back) {
+			step.dist = step.dist.clamp(-self.max_dist, self.max_dist);
+		}
+		self.inner.apply(step)
+	}
+
+	fn position(&self) -> Position {
+		self.inner.position()
+	}
+}
+// end::models[]
+
+// tag::planner[]
+/// `None` if `dist` is `i32::MIN`, as a step's distance can't be negated
+fn move_step(dist: i32) -> Option<Step> {
+	Some(if dist >= 0 {
+		Step {
+			direction: Direction::Forward,
+			dist,
+		}
+	} else {
+		Step {
+			direction: Direction::Back,
+			dist: dist.checked_neg()?,
+		}
+	})
+}
+
+/// `None` if `dist` is `i32::MIN`, as a step's distance can't be negated
+fn vertical_step(dist: i32) -> Option<Step> {
+	Some(if dist >= 0 {
+		Step {
+			direction: Direction::Down,
+			dist,
+		}
+	} else {
+		Step {
+			direction: Direction::Up,
+			dist: dist.checked_neg()?,
+		}
+	})
+}
+
+impl Simple {
+	/// The fewest steps taking `Simple::default()` to `target`, or `None` if either
+	/// coordinate of `target` is `i32::MIN`, which is too far away for a single step
+	pub fn shortest_plan(target: Position) -> Option<Vec<Step>> {
+		let mut steps = Vec::new();
+		if target.h != 0 {
+			steps.push(move_step(target.h)?);
+		}
+		if target.v != 0 {
+			steps.push(vertical_step(target.v)?);
+		}
+		Some(steps)
+	}
+}
+
+impl Aimed {
+	/// The fewest steps taking `Aimed::default()` to `target`. Turning never helps (it just
+	/// swaps forward and back), and a single step can't change the depth, so:
+	/// - if the depth is 0, move straight there
+	/// - if the horizontal distance divides the depth, aim and then move
+	/// - otherwise, move all but one unit, aim at the full depth, and move the last unit
+	///
+	/// `None` if some step of that plan would be too long, which only happens when a
+	/// coordinate of `target` is `i32::MIN`
+	pub fn shortest_plan(Position { h, v }: Position) -> Option<Vec<Step>> {
+		if v == 0 {
+			Simple::shortest_plan(Position { h, v })
+		} else if h != 0 && v.checked_rem(h) == Some(0) {
+			Some(vec![vertical_step(v / h)?, move_step(h)?])
+		} else {
+			// Take the last unit in the direction of `h`, so that the rest of the way is
+			// shorter than `h` and can't overflow
+			let unit = if h < 0 { -1 } else { 1 };
+			Some(vec![
+				move_step(h - unit)?,
+				vertical_step(v.checked_mul(unit)?)?,
+				move_step(unit)?,
+			])
+		}
+	}
+}
+
+/// Every step of length at most `max_dist`, and a single about-face; a reasonable
+/// alphabet of moves for `plan_cheapest`
+pub fn steps_up_to(max_dist: i32) -> Vec<Step> {
+	use Direction::*;
+	let mut steps = vec![Step {
+		direction: Turn,
+		dist: 1,
+	}];
+	for direction in [Forward, Back, Up, Down] {
+		steps.extend((1..=max_dist).map(|dist| Step { direction, dist }));
+	}
+	steps
+}
+
+/// Searches (in order of increasing cost) for the cheapest sequence of `moves` taking
+/// `start` to a state whose position is `target`. As the state space is unbounded,
+/// plans costing more than `max_cost` aren't considered, and `None` means that no plan
+/// within budget exists.
+///
+/// Panics if any of `moves` costs 0, as the search could then wander forever among
+/// states that cost nothing to reach
+pub fn plan_cheapest<M: SteeringModel + Hash + Eq>(
+	start: M,
+	target: Position,
+	moves: &[Step],
+	step_cost: impl Fn(Step) -> u64,
+	max_cost: u64,
+) -> Option<(Vec<Step>, u64)> {
+	for &step in moves {
+		assert!(
+			step_cost(step) > 0,
+			"step costs must be positive, got 0 for {step:?}"
+		);
+	}
+
+	let mut pq = PriorityQueue::new();
+	let mut best_costs = HashMap::new();
+	let mut came_from = HashMap::<M, (M, Step)>::new();
+
+	pq.push(start.clone(), Reverse(0));
+	best_costs.insert(start, 0);
+
+	while let Some((state, Reverse(cost))) = pq.pop() {
+		if state.position() == target {
+			let mut steps = Vec::new();
+			let mut state = &state;
+			while let Some((prev, step)) = came_from.get(state) {
+				steps.push(*step);
+				state = prev;
+			}
+			steps.reverse();
+			return Some((steps, cost));
+		}
+
+		for &step in moves {
+			let new_cost = cost.saturating_add(step_cost(step));
+			if new_cost > max_cost {
+				continue;
+			}
+
+			// States out of range of an `i32` are unreachable
+			let mut new_state = state.clone();
+			if new_state.apply(step).is_none() {
+				continue;
+			}
+			if best_costs
+				.get(&new_state)
+				.is_some_and(|&best| best <= new_cost)
+			{
+				continue;
+			}
+
+			best_costs.insert(new_state.clone(), new_cost);
+			came_from.insert(new_state.clone(), (state.clone(), step));
+			pq.push_increase(new_state, Reverse(new_cost));
+		}
+	}
+
+	None
+}
+// end::planner[]
+
+// tag::script[]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptErrorKind {
+	UnknownCommand(String),
+	MissingArgument,
+	InvalidArgument(String),
+	UnexpectedToken(String),
+	UnmatchedClosingBrace,
+	UnclosedRepeat,
+	TooManySteps,
+	Overflow,
+}
+
+/// `line` is 1-indexed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+	pub line: usize,
+	pub kind: ScriptErrorKind,
+}
+
+impl Display for ScriptError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		use ScriptErrorKind::*;
+		write!(f, "line {}: ", self.line)?;
+		match &self.kind {
+			UnknownCommand(cmd) => write!(f, "unknown command {cmd:?}"),
+			MissingArgument => write!(f, "missing argument"),
+			InvalidArgument(arg) => write!(f, "invalid argument {arg:?}"),
+			UnexpectedToken(tok) => write!(f, "unexpected token {tok:?}"),
+			UnmatchedClosingBrace => write!(f, "`}}` without a matching `repeat`"),
+			UnclosedRepeat => write!(f, "`repeat` block is never closed"),
+			TooManySteps => write!(f, "script expands to more than {MAX_SCRIPT_STEPS} steps"),
+			Overflow => write!(f, "step takes the submarine out of range"),
+		}
+	}
+}
+
+/// The most steps a compiled script may contain; as `repeat` blocks multiply, a short
+/// script could otherwise expand to more steps than fit in memory
+pub const MAX_SCRIPT_STEPS: usize = 1 << 20;
+
+fn parse_arg<T: FromStr>(arg: Option<&str>) -> Result<T, ScriptErrorKind> {
+	let arg = arg.ok_or(ScriptErrorKind::MissingArgument)?;
+	arg.parse()
+		.map_err(|_| ScriptErrorKind::InvalidArgument(arg.to_owned()))
+}
+
+fn expect_end<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Result<(), ScriptErrorKind> {
+	match tokens.next() {
+		Some(tok) => Err(ScriptErrorKind::UnexpectedToken(tok.to_owned())),
+		None => Ok(()),
+	}
+}
+
+/// Compiles a script into the flat list of steps it executes. In addition to the
+/// commands of the puzzle input, a script may contain
+/// - `back N`, which moves opposite the current heading
+/// - `turn [N]`, which makes `N` (default 1) about-faces
+/// - `repeat N {` ... `}` blocks, which may be nested
+/// - `#` comments, which run to the end of the line
+///
+/// # Errors
+///
+/// All lines are checked, and every error found is returned. A block whose expansion
+/// would bring the script past `MAX_SCRIPT_STEPS` steps is reported on its closing line
+pub fn compile_script(script: &str) -> Result<Vec<Step>, Vec<ScriptError>> {
+	Ok(compile_script_lines(script)?
+		.into_iter()
+		.map(|(_, step)| step)
+		.collect())
+}
+
+/// Compiles and runs a script starting from `model`
+///
+/// # Errors
+///
+/// Any errors from `compile_script`; otherwise, if a step overflows, the line it came
+/// from
+pub fn run_script<M: SteeringModel>(mut model: M, script: &str) -> Result<M, Vec<ScriptError>> {
+	for (line, step) in compile_script_lines(script)? {
+		if model.apply(step).is_none() {
+			return Err(vec![ScriptError {
+				line,
+				kind: ScriptErrorKind::Overflow,
+			}]);
+		}
+	}
+	Ok(model)
+}
+
+/// Each compiled step is paired with the line it came from
+fn compile_script_lines(script: &str) -> Result<Vec<(usize, Step)>, Vec<ScriptError>> {
+	use ScriptErrorKind::*;
+
+	// Each frame is (line of its `repeat`, repeat count, steps so far); the bottom frame
+	// is the top level of the script and is never popped
+	let mut frames = vec![(0, 1, Vec::new())];
+	let mut errors = Vec::new();
+
+	for (line_idx, line) in script.lines().enumerate() {
+		let line_no = line_idx + 1;
+		let code = line.split('#').next().unwrap_or_default();
+		let mut tokens = code.split_whitespace();
+		let Some(command) = tokens.next() else {
+			continue;
+		};
+
+		let result = match command {
+			"}" => expect_end(tokens).and_then(|()| {
+				if frames.len() == 1 {
+					return Err(UnmatchedClosingBrace);
+				}
+				let (_, count, body) = frames.pop().unwrap();
+				let outer = &mut frames.last_mut().unwrap().2;
+				match body
+					.len()
+					.checked_mul(count)
+					.and_then(|n| n.checked_add(outer.len()))
+				{
+					Some(n) if n <= MAX_SCRIPT_STEPS => {
+						outer.extend(body.repeat(count));
+						Ok(())
+					}
+					_ => Err(TooManySteps),
+				}
+			}),
+			"repeat" => {
+				let count = parse_arg::<usize>(tokens.next());
+				// Open the block even if the header is malformed so that its `}` still
+				// has something to match
+				frames.push((line_no, *count.as_ref().unwrap_or(&0), Vec::new()));
+				count.and_then(|_| match tokens.next() {
+					Some("{") => expect_end(tokens),
+					Some(tok) => Err(UnexpectedToken(tok.to_owned())),
+					None => Err(MissingArgument),
+				})
+			}
+			_ => Direction::from_str(command)
+				.ok_or_else(|| UnknownCommand(command.to_owned()))
+				.and_then(|direction| {
+					let dist = match (direction, tokens.next()) {
+						(Direction::Turn, None) => 1,
+						(_, arg) => parse_arg(arg)?,
+					};
+					expect_end(tokens)?;
+					frames
+						.last_mut()
+						.unwrap()
+						.2
+						.push((line_no, Step { direction, dist }));
+					Ok(())
+				}),
+		};
+
+		if let Err(kind) = result {
+			errors.push(ScriptError {
+				line: line_no,
+				kind,
+			});
+		}
+	}
+
+	while frames.len() > 1 {
+		let (line, _, _) = frames.pop().unwrap();
+		errors.push(ScriptError {
+			line,
+			kind: UnclosedRepeat,
+		});
+	}
+
+	if errors.is_empty() {
+		Ok(frames.pop().unwrap().2)
+	} else {
+		errors.sort_by_key(|e| e.line);
+		Err(errors)
+	}
+}
+// end::script[]
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -108,4 +648,259 @@ mod test {
 		test_input!(include_str!("sample_input.txt"), day: 2, ans: (150, 900));
 		test_input!(include_str!("input.txt"), day: 2, ans: (1_459_206, 1_320_534_480));
 	}
+
+	#[test]
+	fn test_script() {
+		let steps = compile_script(
+			"# comments are ignored
+			forward 2 # even at the end of a line
+			repeat 2 {
+				down 1
+				repeat 3 {
+					forward 1
+				}
+			}
+			turn
+			back 4",
+		)
+		.unwrap();
+		assert_eq!(steps.len(), 11);
+
+		let trace = trace(Aimed::default(), steps.iter())
+			.unwrap()
+			.into_iter()
+			.map(|m| m.position())
+			.collect::<Vec<_>>();
+		assert_eq!(trace.len(), steps.len());
+		assert_eq!(trace[0], Position { h: 2, v: 0 });
+		assert_eq!(trace[4], Position { h: 5, v: 3 });
+		assert_eq!(trace.last(), Some(&Position { h: 12, v: 1 }));
+		assert_eq!(
+			run(Simple::default(), steps.iter()).unwrap().position(),
+			Position { h: 12, v: 2 }
+		);
+
+		assert_eq!(
+			compile_script(include_str!("sample_input.txt")).map(|steps| pt2(steps.iter())),
+			Ok(Some(900))
+		);
+
+		let errors = compile_script("forward\nsideways 3\nrepeat x {\n}\nup 1 2\n}\nrepeat 2 {")
+			.unwrap_err();
+		assert_eq!(
+			errors
+				.into_iter()
+				.map(|e| (e.line, e.kind))
+				.collect::<Vec<_>>(),
+			vec![
+				(1, ScriptErrorKind::MissingArgument),
+				(2, ScriptErrorKind::UnknownCommand("sideways".to_owned())),
+				(3, ScriptErrorKind::InvalidArgument("x".to_owned())),
+				(5, ScriptErrorKind::UnexpectedToken("2".to_owned())),
+				(6, ScriptErrorKind::UnmatchedClosingBrace),
+				(7, ScriptErrorKind::UnclosedRepeat),
+			]
+		);
+
+		let nested = "repeat 1000 {\nrepeat 1000 {\nforward 1\n}\n}";
+		assert_eq!(
+			compile_script(nested).map(|steps| steps.len()),
+			Ok(1_000_000)
+		);
+		for script in [
+			"repeat 4000000000 {\nforward 1\n}".to_owned(),
+			format!("repeat 1000 {{\n{nested}\n}}"),
+		] {
+			let errors = compile_script(&script).unwrap_err();
+			assert_eq!(
+				errors
+					.into_iter()
+					.map(|e| (e.line, e.kind))
+					.collect::<Vec<_>>(),
+				vec![(script.lines().count(), ScriptErrorKind::TooManySteps)]
+			);
+		}
+		assert_eq!(compile_script("repeat 4000000000 {\n}"), Ok(Vec::new()));
+
+		let far = "repeat 1000000 {\nforward 10000\n}";
+		assert_eq!(compile_script(far).map(|steps| steps.len()), Ok(1_000_000));
+		assert_eq!(
+			run_script(Simple::default(), far),
+			Err(vec![ScriptError {
+				line: 2,
+				kind: ScriptErrorKind::Overflow
+			}])
+		);
+		assert_eq!(
+			run_script(
+				Aimed::default(),
+				"down 2\nforward 3\ndown 1\n\nback 1000000000 # too far"
+			),
+			Err(vec![ScriptError {
+				line: 5,
+				kind: ScriptErrorKind::Overflow
+			}])
+		);
+		assert_eq!(
+			run_script(Aimed::default(), include_str!("sample_input.txt")).map(|m| m.get_ans()),
+			Ok(Some(900))
+		);
+	}
+
+	#[test]
+	fn test_models() {
+		let steps = read_input(include_str!("sample_input.txt")).unwrap();
+
+		let drifting = run(Drifting::new(-2), steps.iter()).unwrap();
+		assert_eq!(
+			drifting.position(),
+			run(Aimed::default(), steps.iter()).unwrap().pos
+		);
+		assert_eq!(drifting.lateral, -30);
+
+		let limited = run(SpeedLimited::new(Simple::default(), 5), steps.iter()).unwrap();
+		assert_eq!(limited.position(), Position { h: 12, v: 10 });
+		assert_eq!(limited.get_ans(), Some(120));
+	}
+
+	#[test]
+	fn test_overflow() {
+		let forward = |dist| Step {
+			direction: Direction::Forward,
+			dist,
+		};
+		let down = |dist| Step {
+			direction: Direction::Down,
+			dist,
+		};
+
+		let steps = [forward(i32::MAX), forward(1)];
+		assert_eq!(
+			run(Simple::default(), steps.iter()),
+			Err(Overflow { step: 1 })
+		);
+		assert_eq!(
+			trace(Simple::default(), steps.iter()),
+			Err(Overflow { step: 1 })
+		);
+		assert_eq!(pt1(steps.iter()), None);
+
+		// A failed step leaves the state as it was
+		let mut aimed = run(Aimed::default(), [down(2), forward(1 << 29)].iter()).unwrap();
+		assert_eq!(aimed.apply(forward(1 << 29)), None);
+		assert_eq!(
+			aimed.pos,
+			Position {
+				h: 1 << 29,
+				v: 1 << 30
+			}
+		);
+		assert_eq!(aimed.get_ans(), None);
+
+		let mut drifting = Drifting::new(i32::MAX);
+		assert_eq!(drifting.apply(forward(2)), None);
+		assert_eq!(drifting, Drifting::new(i32::MAX));
+		assert_eq!(drifting.apply(forward(i32::MIN)), None);
+
+		let limited = SpeedLimited::new(Simple::default(), 5);
+		assert_eq!(
+			run(limited, [forward(i32::MAX); 10].iter()).map(|m| m.position()),
+			Ok(Position { h: 50, v: 0 })
+		);
+	}
+
+	#[test]
+	#[should_panic = "speed limit must not be negative"]
+	fn test_negative_speed_limit() {
+		SpeedLimited::new(Simple::default(), -1);
+	}
+
+	#[test]
+	fn test_planner() {
+		let moves = steps_up_to(8);
+		for h in -2..=2 {
+			for v in -3..=3 {
+				let target = Position { h, v };
+
+				let simple_plan = Simple::shortest_plan(target).unwrap();
+				assert_eq!(
+					run(Simple::default(), simple_plan.iter()).unwrap().pos,
+					target
+				);
+
+				let aimed_plan = Aimed::shortest_plan(target).unwrap();
+				assert_eq!(
+					run(Aimed::default(), aimed_plan.iter()).unwrap().pos,
+					target
+				);
+
+				let (searched_plan, n_steps) =
+					plan_cheapest(Aimed::default(), target, &moves, |_| 1, 3).unwrap();
+				assert_eq!(
+					run(Aimed::default(), searched_plan.iter()).unwrap().pos,
+					target
+				);
+				assert_eq!(usize::try_from(n_steps).unwrap(), aimed_plan.len());
+			}
+		}
+
+		let target = run(
+			Aimed::default(),
+			read_input(include_str!("input.txt")).unwrap().iter(),
+		)
+		.unwrap()
+		.pos;
+		let plan = Aimed::shortest_plan(target).unwrap();
+		assert!(plan.len() <= 3);
+		assert_eq!(
+			run(Aimed::default(), plan.iter()).unwrap().get_ans(),
+			Some(1_320_534_480)
+		);
+
+		// Charging by distance, it's cheaper to reach depth 4 via aim 2 than via aim 4
+		let (plan, fuel) = plan_cheapest(
+			Aimed::default(),
+			Position { h: 2, v: 4 },
+			&steps_up_to(4),
+			|step| u64::try_from(step.dist).unwrap(),
+			10,
+		)
+		.unwrap();
+		assert_eq!(fuel, 4);
+		assert_eq!(
+			run(Aimed::default(), plan.iter()).unwrap().pos,
+			Position { h: 2, v: 4 }
+		);
+
+		let extremes = [i32::MIN, i32::MIN + 1, -1, 0, 1, i32::MAX];
+		for h in extremes {
+			for v in extremes {
+				let target = Position { h, v };
+				let in_range = h != i32::MIN && v != i32::MIN;
+				let simple_plan = Simple::shortest_plan(target);
+				assert_eq!(simple_plan.is_some(), in_range, "{target:?}");
+				if let Some(plan) = simple_plan {
+					assert_eq!(run(Simple::default(), plan.iter()).unwrap().pos, target);
+				}
+
+				let aimed_plan = Aimed::shortest_plan(target);
+				assert!(aimed_plan.is_some() || !in_range, "{target:?}");
+				if let Some(plan) = aimed_plan {
+					assert_eq!(run(Aimed::default(), plan.iter()).unwrap().pos, target);
+				}
+			}
+		}
+	}
+
+	#[test]
+	#[should_panic = "step costs must be positive"]
+	fn test_planner_zero_cost() {
+		plan_cheapest(
+			Aimed::default(),
+			Position { h: 1, v: 1 },
+			&steps_up_to(2),
+			|step| u64::from(step.direction != Direction::Turn),
+			10,
+		);
+	}
 }
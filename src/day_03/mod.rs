@@ -1,114 +1,456 @@
 // tag::setup[]
-use crate::{utils::to_decimal, Answer};
-use ndarray::prelude::*;
+use crate::Answer;
+use num::BigUint;
 
-fn read_input(input: &str) -> Option<ndarray::Array2<bool>> {
-	let mut lines = input.lines();
-	let mut bit_vec = Vec::new();
+type Word = u64;
+const WORD_BITS: usize = Word::BITS as usize;
+pub const MAX_WIDTH: usize = u128::BITS as usize;
 
-	let first_line = lines.next()?;
-	let line_length = first_line.len();
+/// A diagnostic report, stored both row-wise (each row packed into a `u128`, most
+/// significant digit first) and column-wise (each column a bitset over the rows). The
+/// column bitsets let us count digits and filter rows a whole word at a time
+#[derive(Debug, Clone)]
+pub struct Report {
+	width: usize,
+	rows: Vec<u128>,
+	columns: Vec<Vec<Word>>,
+}
+
+impl Report {
+	pub fn parse(input: &str) -> Option<Self> {
+		let mut lines = input.lines().map(str::trim).filter(|line| !line.is_empty());
+		let first_line = lines.next()?;
+		let width = first_line.len();
+		if width == 0 || width > MAX_WIDTH {
+			return None;
+		}
+
+		let rows = std::iter::once(first_line)
+			.chain(lines)
+			.map(|line| {
+				if line.len() != width {
+					return None;
+				}
+				line.bytes().try_fold(0_u128, |row, c| match c {
+					b'0' => Some(row << 1),
+					b'1' => Some((row << 1) | 1),
+					_ => None,
+				})
+			})
+			.collect::<Option<Vec<_>>>()?;
+
+		Some(Self::from_rows(width, rows))
+	}
+
+	/// `rows` must not have any bits set at or above `width`
+	pub fn from_rows(width: usize, rows: Vec<u128>) -> Self {
+		assert!(0 < width && width <= MAX_WIDTH);
+
+		let n_words = (rows.len() + WORD_BITS - 1) / WORD_BITS;
+		let mut columns = vec![vec![0; n_words]; width];
+		for (row_idx, &row) in rows.iter().enumerate() {
+			let (word_idx, bit) = (row_idx / WORD_BITS, row_idx % WORD_BITS);
+			for (col_idx, column) in columns.iter_mut().enumerate() {
+				if (row >> (width - 1 - col_idx)) & 1 == 1 {
+					column[word_idx] |= 1 << bit;
+				}
+			}
+		}
 
-	for line in std::iter::once(first_line).chain(lines) {
-		for c in line.bytes() {
-			bit_vec.push(c == b'1');
+		Self {
+			width,
+			rows,
+			columns,
 		}
 	}
 
-	let n_lines = bit_vec.len() / line_length;
-	Array2::from_shape_vec((n_lines, line_length), bit_vec).ok()
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	pub fn n_rows(&self) -> usize {
+		self.rows.len()
+	}
+
+	pub fn rows(&self) -> &[u128] {
+		&self.rows
+	}
+
+	fn all_ones(&self) -> u128 {
+		u128::MAX >> (MAX_WIDTH - self.width)
+	}
+
+	/// A bitset with one bit set for each row
+	fn all_rows(&self) -> Vec<Word> {
+		let n_rows = self.n_rows();
+		let mut bitset = vec![Word::MAX; (n_rows + WORD_BITS - 1) / WORD_BITS];
+		if n_rows % WORD_BITS != 0 {
+			*bitset.last_mut().unwrap() = (1 << (n_rows % WORD_BITS)) - 1;
+		}
+		bitset
+	}
 }
 
-fn ans_for_input(input: &str) -> Answer<u32, u32> {
-	let mat = read_input(input).unwrap();
-	(3, (pt1(&mat), pt2(&mat))).into()
+fn count_ones(bitset: &[Word]) -> usize {
+	bitset.iter().map(|w| w.count_ones() as usize).sum()
 }
 
-pub fn ans() -> Answer<u32, u32> {
+fn ans_for_input(input: &str) -> Answer<BigUint, BigUint> {
+	let report = Report::parse(input).unwrap();
+	(3, (pt1(&report), pt2(&report).unwrap())).into()
+}
+
+pub fn ans() -> Answer<BigUint, BigUint> {
 	ans_for_input(include_str!("input.txt"))
 }
 // end::setup[]
 
 // tag::pt1[]
-fn pt1(mat: &Array2<bool>) -> u32 {
-	let (n_rows, n_cols) = mat.dim();
-
-	let n_ones = mat.map(|x| usize::from(*x)).sum_axis(Axis(0));
-	let n_zeros = n_ones.map(|n| n_rows - n);
+impl Report {
+	/// Returns `(gamma_rate, epsilon_rate)`
+	pub fn gamma_epsilon(&self) -> (u128, u128) {
+		let n_rows = self.n_rows();
+		let gamma_rate = self.columns.iter().fold(0_u128, |gamma, column| {
+			let n_ones = count_ones(column);
+			(gamma << 1) | u128::from(n_ones > n_rows - n_ones)
+		});
+		let epsilon_rate = self.all_ones() - gamma_rate;
 
-	let col_has_more_ones_than_zeros = ndarray::Zip::from(&n_ones)
-		.and(&n_zeros)
-		.map_collect(|n_o, n_z| n_o > n_z)
-		.into_shape((n_cols,))
-		.unwrap();
-
-	let gamma_rate = to_decimal(col_has_more_ones_than_zeros.to_vec());
-	let epsilon_rate = (2u32.pow(u32::try_from(n_cols).unwrap()) - 1) - gamma_rate;
+		(gamma_rate, epsilon_rate)
+	}
+}
 
-	gamma_rate * epsilon_rate
+// Rates can be up to 128 bits wide, so their product can take up to 256
+fn pt1(report: &Report) -> BigUint {
+	let (gamma_rate, epsilon_rate) = report.gamma_epsilon();
+	BigUint::from(gamma_rate) * epsilon_rate
 }
 // end::pt1[]
 
 // tag::pt2[]
-fn value_of_line_chosen_by_criterion(
-	mat: &Array2<bool>,
-	cmp_predicate: impl Fn(usize, usize) -> bool,
-) -> u32 {
-	let (n_rows, n_cols) = mat.dim();
-	let mut candidates = Array1::<_>::from_shape_simple_fn((n_rows,), || true);
-	for i in 0..n_cols {
-		let n_candidates_remaining = candidates.mapv(|c| if c { 1usize } else { 0 }).sum();
-
-		if n_candidates_remaining == 1 {
-			break;
+impl Report {
+	/// Filters the rows column by column, keeping those whose digit is the most common
+	/// one among the remaining rows if `cmp_predicate(2 * n_ones, n_remaining)`, and the
+	/// least common one otherwise. A column in which all remaining rows have the same
+	/// digit is skipped, as filtering by its absent digit would leave no rows at all; this
+	/// agrees with `ReportTrie::select`, and means that the result is `None` only if the
+	/// report is empty
+	pub fn value_of_line_chosen_by_criterion(
+		&self,
+		cmp_predicate: impl Fn(usize, usize) -> bool,
+	) -> Option<u128> {
+		let mut candidates = self.all_rows();
+		let mut n_candidates_remaining = self.n_rows();
+
+		for column in &self.columns {
+			if n_candidates_remaining <= 1 {
+				break;
+			}
+
+			let digit_sum = candidates
+				.iter()
+				.zip(column)
+				.map(|(c, d)| (c & d).count_ones() as usize)
+				.sum::<usize>();
+			if digit_sum == 0 || digit_sum == n_candidates_remaining {
+				continue;
+			}
+
+			let most_common_digit = cmp_predicate(2 * digit_sum, n_candidates_remaining);
+
+			for (c, &d) in candidates.iter_mut().zip(column) {
+				*c &= if most_common_digit { d } else { !d };
+			}
+			n_candidates_remaining = if most_common_digit {
+				digit_sum
+			} else {
+				n_candidates_remaining - digit_sum
+			};
 		}
 
-		let column = mat.index_axis(Axis(1), i);
-		let digit_sum = column
-			.iter()
-			.enumerate()
-			.filter_map(|(i, &x)| {
-				if candidates[[i]] {
-					Some(usize::from(x))
-				} else {
-					None
-				}
-			})
-			.sum::<usize>();
+		let (word_idx, word) = candidates.iter().enumerate().find(|(_, &w)| w != 0)?;
+		let index = word_idx * WORD_BITS + word.trailing_zeros() as usize;
+		Some(self.rows[index])
+	}
+
+	/// Returns `(oxy_rate, co2_rate)`
+	pub fn oxy_co2(&self) -> Option<(u128, u128)> {
+		let oxy_rate = self.value_of_line_chosen_by_criterion(|x, y| x >= y)?;
+		let co2_rate = self.value_of_line_chosen_by_criterion(|x, y| x < y)?;
+		Some((oxy_rate, co2_rate))
+	}
+}
 
-		let most_common_digit = cmp_predicate(2 * digit_sum, n_candidates_remaining);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Commonality {
+	Most,
+	Least,
+}
+
+/// Which digit wins when `0` and `1` are equally common
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+	Zero,
+	One,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitCriterion {
+	pub keep: Commonality,
+	pub on_tie: TieBreak,
+}
 
-		candidates = ndarray::Zip::from(&candidates)
-			.and(&column)
-			.map_collect(|&candidate, &digit| candidate && digit == most_common_digit);
+impl BitCriterion {
+	pub const OXYGEN: Self = Self {
+		keep: Commonality::Most,
+		on_tie: TieBreak::One,
+	};
+	pub const CO2: Self = Self {
+		keep: Commonality::Least,
+		on_tie: TieBreak::Zero,
+	};
+
+	fn choose(self, n_zeros: usize, n_ones: usize) -> usize {
+		use std::cmp::Ordering::*;
+		let choose_one = match n_ones.cmp(&n_zeros) {
+			Greater => self.keep == Commonality::Most,
+			Less => self.keep == Commonality::Least,
+			Equal => self.on_tie == TieBreak::One,
+		};
+		usize::from(choose_one)
 	}
+}
 
-	let index = candidates
-		.into_iter()
-		.enumerate()
-		.find_map(|(i, x)| if x { Some(i) } else { None })
-		.unwrap();
-	let line = mat.index_axis(Axis(0), index);
+#[derive(Debug, Clone, Copy)]
+struct TrieNode {
+	// 0 means "no child", as the root is nobody's child
+	children: [usize; 2],
+	count: usize,
+}
 
-	to_decimal(line.to_vec())
+/// A binary trie of a report's rows, most significant digit at the top. Each node knows
+/// how many rows pass through it, so each step of part 2's filtering is a single
+/// comparison and a whole query takes time proportional to the width, regardless of the
+/// number of rows.
+///
+/// Building the trie costs up to one node per digit of the report, which for a large,
+/// wide report is far more than the report itself; for a single query, filtering the
+/// report's column bitsets is both faster and leaner. The trie pays off when making many
+/// queries, or queries with per-column criteria
+#[derive(Debug, Clone)]
+pub struct ReportTrie {
+	width: usize,
+	nodes: Vec<TrieNode>,
 }
 
-fn pt2(mat: &Array2<bool>) -> u32 {
-	let [oxy_rate, co2_rate] =
-		[|x, y| x >= y, |x, y| x < y].map(|op| value_of_line_chosen_by_criterion(mat, op));
+impl ReportTrie {
+	pub fn new(report: &Report) -> Self {
+		let width = report.width();
+		let empty = TrieNode {
+			children: [0; 2],
+			count: 0,
+		};
+		let mut nodes = vec![empty];
+
+		for &row in report.rows() {
+			let mut node_idx = 0;
+			nodes[0].count += 1;
+			for bit_idx in (0..width).rev() {
+				let digit = usize::try_from((row >> bit_idx) & 1).unwrap();
+				let mut child_idx = nodes[node_idx].children[digit];
+				if child_idx == 0 {
+					child_idx = nodes.len();
+					nodes[node_idx].children[digit] = child_idx;
+					nodes.push(empty);
+				}
+				node_idx = child_idx;
+				nodes[node_idx].count += 1;
+			}
+		}
+
+		Self { width, nodes }
+	}
+
+	pub fn width(&self) -> usize {
+		self.width
+	}
+
+	fn child_count(&self, node_idx: usize, digit: usize) -> usize {
+		match self.nodes[node_idx].children[digit] {
+			0 => 0,
+			child_idx => self.nodes[child_idx].count,
+		}
+	}
+
+	/// Walks down the trie, at column `i` keeping the digit chosen by `criterion_for_col(i)`
+	/// among the rows remaining. A digit no remaining row has is never chosen, so once a
+	/// single row remains (or all remaining rows are equal) we just follow it to the
+	/// bottom. Returns `None` only if the report is empty
+	pub fn select_with(
+		&self,
+		mut criterion_for_col: impl FnMut(usize) -> BitCriterion,
+	) -> Option<u128> {
+		if self.nodes[0].count == 0 {
+			return None;
+		}
+
+		let mut node_idx = 0;
+		let mut value = 0_u128;
+		for col in 0..self.width {
+			let [n_zeros, n_ones] = [0, 1].map(|digit| self.child_count(node_idx, digit));
+			let digit = match (n_zeros, n_ones) {
+				(0, _) => 1,
+				(_, 0) => 0,
+				_ => criterion_for_col(col).choose(n_zeros, n_ones),
+			};
+			node_idx = self.nodes[node_idx].children[digit];
+			value = (value << 1) | digit as u128;
+		}
+
+		Some(value)
+	}
+
+	pub fn select(&self, criterion: BitCriterion) -> Option<u128> {
+		self.select_with(|_| criterion)
+	}
+
+	/// The number of rows whose first `len` digits are the `len` digits of `prefix`
+	pub fn count_with_prefix(&self, prefix: u128, len: usize) -> usize {
+		assert!(len <= self.width);
+		let mut node_idx = 0;
+		for bit_idx in (0..len).rev() {
+			let digit = usize::try_from((prefix >> bit_idx) & 1).unwrap();
+			match self.nodes[node_idx].children[digit] {
+				0 => return 0,
+				child_idx => node_idx = child_idx,
+			}
+		}
+		self.nodes[node_idx].count
+	}
+}
 
-	oxy_rate * co2_rate
+fn pt2(report: &Report) -> Option<BigUint> {
+	let (oxy_rate, co2_rate) = report.oxy_co2()?;
+	Some(BigUint::from(oxy_rate) * co2_rate)
 }
 // end::pt2[]
 
 #[cfg(test)]
 mod test {
 	use super::*;
-	use crate::test_input;
+	use crate::{test_input, utils::SplitMix64};
 
 	#[test]
 	fn test() {
-		test_input!(include_str!("sample_input.txt"), day: 3, ans: (198, 230));
-		test_input!(include_str!("input.txt"), day: 3, ans: (2_743_844, 6_677_951));
+		test_input!(
+			include_str!("sample_input.txt"),
+			day: 3,
+			ans: (BigUint::from(198_u32), BigUint::from(230_u32))
+		);
+		test_input!(
+			include_str!("input.txt"),
+			day: 3,
+			ans: (BigUint::from(2_743_844_u32), BigUint::from(6_677_951_u32))
+		);
+	}
+
+	#[test]
+	fn test_wide() {
+		// Repeating each row 25 times doesn't change which rows are chosen, but makes every
+		// value 125 bits wide
+		let n_repeats = 25;
+		let sample = include_str!("sample_input.txt");
+		let wide_input = sample
+			.lines()
+			.map(|line| line.repeat(n_repeats))
+			.collect::<Vec<_>>()
+			.join("\n");
+		let repeat = |val: u128| (0..n_repeats).fold(0, |acc, _| (acc << 5) | val);
+
+		let narrow = Report::parse(sample).unwrap();
+		let wide = Report::parse(&wide_input).unwrap();
+		assert_eq!(wide.width(), 125);
+
+		let (gamma, epsilon) = narrow.gamma_epsilon();
+		assert_eq!(wide.gamma_epsilon(), (repeat(gamma), repeat(epsilon)));
+
+		let (oxy, co2) = narrow.oxy_co2().unwrap();
+		assert_eq!(wide.oxy_co2(), Some((repeat(oxy), repeat(co2))));
+
+		// The products no longer fit in a `u128`
+		assert_eq!(
+			pt1(&wide),
+			BigUint::from(repeat(gamma)) * BigUint::from(repeat(epsilon))
+		);
+		assert_eq!(
+			pt2(&wide),
+			Some(BigUint::from(repeat(oxy)) * BigUint::from(repeat(co2)))
+		);
+		assert!(pt1(&wide).bits() > 128 && pt2(&wide).unwrap().bits() > 128);
+
+		assert!(Report::parse(&"1".repeat(129)).is_none());
+		assert_eq!(
+			Report::parse(&"1".repeat(128)).unwrap().gamma_epsilon(),
+			(u128::MAX, 0)
+		);
+	}
+
+	#[test]
+	fn test_trie() {
+		let cross_check = |report: &Report| {
+			let trie = ReportTrie::new(report);
+			assert_eq!(
+				trie.select(BitCriterion::OXYGEN),
+				report.value_of_line_chosen_by_criterion(|x, y| x >= y)
+			);
+			assert_eq!(
+				trie.select(BitCriterion::CO2),
+				report.value_of_line_chosen_by_criterion(|x, y| x < y)
+			);
+		};
+
+		// Duplicate rows can agree in every column, which the filter must not hold against
+		// them
+		let duplicates = Report::parse("11\n11").unwrap();
+		cross_check(&duplicates);
+		assert_eq!(duplicates.oxy_co2(), Some((0b11, 0b11)));
+		assert_eq!(pt2(&duplicates), Some(BigUint::from(9_u32)));
+		assert_eq!(pt2(&Report::from_rows(3, Vec::new())), None);
+		let sample = include_str!("sample_input.txt");
+		cross_check(&Report::parse(&format!("{sample}\n{sample}")).unwrap());
+
+		let mut rng = SplitMix64::new(3);
+		for _ in 0..1000 {
+			let width = 1 + rng.below(8);
+			let n_rows = 1 + rng.below(40);
+			let rows = (0..n_rows)
+				.map(|_| u128::try_from(rng.below(1 << width)).unwrap())
+				.collect();
+			cross_check(&Report::from_rows(width, rows));
+		}
+
+		let sample = ReportTrie::new(&Report::parse(include_str!("sample_input.txt")).unwrap());
+		assert_eq!(sample.count_with_prefix(0b1, 1), 7);
+		assert_eq!(sample.count_with_prefix(0b10, 2), 4);
+		assert_eq!(sample.count_with_prefix(0b10111, 5), 1);
+		assert_eq!(sample.count_with_prefix(0b11111, 5), 0);
+
+		// Filtering leaves 10110 and 10111 tied at the last digit, which now goes to 0
+		let oxy_preferring_zero = BitCriterion {
+			on_tie: TieBreak::Zero,
+			..BitCriterion::OXYGEN
+		};
+		assert_eq!(sample.select(BitCriterion::OXYGEN), Some(0b10111));
+		assert_eq!(sample.select(oxy_preferring_zero), Some(0b10110));
+
+		// Alternating most- and least-common digits
+		let alternating = sample.select_with(|col| {
+			if col % 2 == 0 {
+				BitCriterion::OXYGEN
+			} else {
+				BitCriterion::CO2
+			}
+		});
+		assert_eq!(alternating, Some(0b11100));
 	}
 }
@@ -1,46 +1,175 @@
 // tag::setup[]
-use crate::Answer;
+use crate::{utils::SplitMix64, Answer};
 use num::{integer::div_mod_floor, Integer};
-use std::{
-	collections::{BTreeMap as Map, BTreeSet as Set},
-	str::FromStr,
-};
+use std::{collections::BTreeMap as Map, str::FromStr};
+
+// end::setup[]
+
+// tag::patterns[]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinPattern {
+	Row(usize),
+	Col(usize),
+	/// From the top left corner down and to the right, stopping at the bottom or right
+	/// edge (whichever comes first)
+	Diagonal,
+	/// From the top right corner down and to the left, stopping at the bottom or left
+	/// edge (whichever comes first)
+	AntiDiagonal,
+	Corners,
+	Blackout,
+	/// The user-defined mask at this index in `WinRules::masks`
+	Mask(usize),
+}
+
+/// Which patterns of marked cells win. When several patterns are completed by the same
+/// number, the win is attributed to the first of them in the order of `WinPattern`'s
+/// variants
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WinRules {
+	pub rows: bool,
+	pub cols: bool,
+	pub diagonals: bool,
+	pub corners: bool,
+	pub blackout: bool,
+	/// Each mask is a list of `(row, col)` cells. A mask only applies to boards it fits on
+	pub masks: Vec<Vec<(usize, usize)>>,
+}
+
+impl Default for WinRules {
+	/// The rules of the puzzle: any complete row or column wins
+	fn default() -> Self {
+		Self {
+			rows: true,
+			cols: true,
+			diagonals: false,
+			corners: false,
+			blackout: false,
+			masks: Vec::new(),
+		}
+	}
+}
+
+impl WinRules {
+	fn patterns(&self, n_rows: usize, n_cols: usize) -> Vec<(WinPattern, Vec<(usize, usize)>)> {
+		let mut patterns = Vec::new();
+		let n_diag = n_rows.min(n_cols);
+
+		if self.rows {
+			patterns.extend(
+				(0..n_rows).map(|r| (WinPattern::Row(r), (0..n_cols).map(|c| (r, c)).collect())),
+			);
+		}
+		if self.cols {
+			patterns.extend(
+				(0..n_cols).map(|c| (WinPattern::Col(c), (0..n_rows).map(|r| (r, c)).collect())),
+			);
+		}
+		if self.diagonals {
+			patterns.push((WinPattern::Diagonal, (0..n_diag).map(|i| (i, i)).collect()));
+			patterns.push((
+				WinPattern::AntiDiagonal,
+				(0..n_diag).map(|i| (i, n_cols - 1 - i)).collect(),
+			));
+		}
+		if self.corners {
+			patterns.push((
+				WinPattern::Corners,
+				vec![
+					(0, 0),
+					(0, n_cols - 1),
+					(n_rows - 1, 0),
+					(n_rows - 1, n_cols - 1),
+				],
+			));
+		}
+		if self.blackout {
+			patterns.push((
+				WinPattern::Blackout,
+				(0..n_rows)
+					.flat_map(|r| (0..n_cols).map(move |c| (r, c)))
+					.collect(),
+			));
+		}
+		for (i, mask) in self.masks.iter().enumerate() {
+			if mask.iter().all(|&(r, c)| r < n_rows && c < n_cols) {
+				patterns.push((WinPattern::Mask(i), mask.clone()));
+			}
+		}
+
+		// Corners of a single row or column overlap, as can cells of a mask
+		for (_, cells) in &mut patterns {
+			cells.sort_unstable();
+			cells.dedup();
+		}
+		patterns.retain(|(_, cells)| !cells.is_empty());
+
+		patterns
+	}
+}
 
 #[derive(Debug)]
 struct BoardProgress {
-	rows: Vec<usize>,
-	cols: Vec<usize>,
-	has_won: bool,
+	n_cols: usize,
+	patterns: Vec<WinPattern>,
+	/// For each pattern, the number of its cells not yet drawn
+	n_remaining: Vec<usize>,
+	/// For each cell (in row-major order), the indices of the patterns containing it
+	patterns_by_cell: Vec<Vec<usize>>,
+	winning_pattern: Option<WinPattern>,
 }
 
 impl BoardProgress {
-	fn new(n_rows: usize, n_cols: usize) -> Self {
-		let rows = vec![n_cols; n_rows];
-		let cols = vec![n_rows; n_cols];
+	fn new(n_rows: usize, n_cols: usize, rules: &WinRules) -> Self {
+		let mut patterns = Vec::new();
+		let mut n_remaining = Vec::new();
+		let mut patterns_by_cell = vec![Vec::new(); n_rows * n_cols];
+
+		for (pattern_idx, (pattern, cells)) in
+			rules.patterns(n_rows, n_cols).into_iter().enumerate()
+		{
+			patterns.push(pattern);
+			n_remaining.push(cells.len());
+			for (r, c) in cells {
+				patterns_by_cell[r * n_cols + c].push(pattern_idx);
+			}
+		}
+
 		Self {
-			rows,
-			cols,
-			has_won: false,
+			n_cols,
+			patterns,
+			n_remaining,
+			patterns_by_cell,
+			winning_pattern: None,
 		}
 	}
 
-	fn handle_entry(&mut self, row: usize, col: usize) {
-		if self.has_won {
-			return;
+	/// Returns the pattern completed by this entry, if it's the one that made the board
+	/// win
+	fn handle_entry(&mut self, row: usize, col: usize) -> Option<WinPattern> {
+		if self.winning_pattern.is_some() {
+			return None;
 		}
 
-		self.rows[row] -= 1;
-		self.cols[col] -= 1;
-
-		if self.rows[row] == 0 || self.cols[col] == 0 {
-			self.has_won = true;
+		for &pattern_idx in &self.patterns_by_cell[row * self.n_cols + col] {
+			self.n_remaining[pattern_idx] -= 1;
+			if self.n_remaining[pattern_idx] == 0 && self.winning_pattern.is_none() {
+				self.winning_pattern = Some(self.patterns[pattern_idx]);
+			}
 		}
+
+		self.winning_pattern
 	}
 }
+// end::patterns[]
 
+// tag::setup[]
 #[derive(Debug)]
 struct Board<T: Integer> {
 	grid: Map<T, (usize, usize)>,
+	n_rows: usize,
+	n_cols: usize,
 	progress: BoardProgress,
 }
 
@@ -56,20 +185,20 @@ impl<T: Integer + std::iter::Sum + Copy> Board<T> {
 		}
 		Self {
 			grid,
-			progress: BoardProgress::new(n_rows, n_cols),
+			n_rows,
+			n_cols,
+			progress: BoardProgress::new(n_rows, n_cols, &WinRules::default()),
 		}
 	}
 
-	fn play_number(&mut self, n: T) {
-		let (r, c) = match self.grid.remove(&n) {
-			Some(coords) => coords,
-			None => return,
-		};
-		self.progress.handle_entry(r, c);
+	/// Returns the winning pattern if playing `n` made this board win
+	fn play_number(&mut self, n: T) -> Option<WinPattern> {
+		let (r, c) = self.grid.remove(&n)?;
+		self.progress.handle_entry(r, c)
 	}
 
 	fn has_won(&self) -> bool {
-		self.progress.has_won
+		self.progress.winning_pattern.is_some()
 	}
 
 	fn get_ans(&self, winning_num: T) -> T {
@@ -78,13 +207,13 @@ impl<T: Integer + std::iter::Sum + Copy> Board<T> {
 	}
 }
 
-struct Game<T: Integer> {
+pub struct Game<T: Integer> {
 	boards: Vec<Board<T>>,
 	numbers: Vec<T>,
 }
 
 impl<T: Integer + std::iter::Sum + Copy + FromStr + std::fmt::Debug> Game<T> {
-	fn from_str(s: &str) -> Option<Self> {
+	pub fn parse(s: &str) -> Option<Self> {
 		let mut lines = s.lines().chain(std::iter::once(""));
 		let nums = lines
 			.next()?
@@ -122,9 +251,28 @@ impl<T: Integer + std::iter::Sum + Copy + FromStr + std::fmt::Debug> Game<T> {
 	}
 }
 
+impl<T: Integer> Game<T> {
+	/// Replaces the rules the boards are played by. Only meaningful before any numbers are
+	/// drawn
+	#[must_use]
+	pub fn with_rules(mut self, rules: &WinRules) -> Self {
+		for board in &mut self.boards {
+			board.progress = BoardProgress::new(board.n_rows, board.n_cols, rules);
+		}
+		self
+	}
+}
+
 fn ans_for_input(input: &str) -> Answer<i32, i32> {
-	let [game1, game2] = [0; 2].map(|_| Game::from_str(input).unwrap());
-	(4, (pt1(game1), pt2(game2))).into()
+	let tournament = Game::parse(input).unwrap().play_tournament();
+	(
+		4,
+		(
+			pt1(&tournament).expect("no board ever won"),
+			pt2(&tournament).expect("no board ever won"),
+		),
+	)
+		.into()
 }
 
 pub fn ans() -> Answer<i32, i32> {
@@ -132,42 +280,331 @@ pub fn ans() -> Answer<i32, i32> {
 }
 // end::setup[]
 
-// tag::pt1[]
-fn pt1(mut game: Game<i32>) -> i32 {
-	for &num in &game.numbers {
-		for board in &mut game.boards {
-			board.play_number(num);
-			if board.has_won() {
-				return board.get_ans(num);
+// tag::tournament[]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Win<T> {
+	pub board_idx: usize,
+	/// The index of the winning number in the list of drawn numbers
+	pub turn: usize,
+	pub winning_num: T,
+	pub pattern: WinPattern,
+	pub score: T,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tournament<T> {
+	/// In the order in which the boards won; boards winning on the same turn are in board
+	/// order
+	pub wins: Vec<Win<T>>,
+	pub never_won: Vec<usize>,
+}
+
+impl<T: Integer + std::iter::Sum + Copy> Game<T> {
+	/// Draws every number (stopping early once all boards have won), recording each
+	/// board's win as it happens
+	pub fn play_tournament(mut self) -> Tournament<T> {
+		let mut wins = Vec::new();
+
+		for (turn, &num) in self.numbers.iter().enumerate() {
+			if wins.len() == self.boards.len() {
+				break;
+			}
+
+			for (board_idx, board) in self.boards.iter_mut().enumerate() {
+				if let Some(pattern) = board.play_number(num) {
+					wins.push(Win {
+						board_idx,
+						turn,
+						winning_num: num,
+						pattern,
+						score: board.get_ans(num),
+					});
+				}
 			}
 		}
+
+		let never_won = self
+			.boards
+			.iter()
+			.enumerate()
+			.filter_map(|(idx, board)| (!board.has_won()).then_some(idx))
+			.collect();
+
+		Tournament { wins, never_won }
 	}
-	unreachable!();
 }
-// end::pt1[]
+// end::tournament[]
 
-// tag::pt2[]
-fn pt2(mut game: Game<i32>) -> i32 {
-	let mut ongoing_game_idxs = (0..game.boards.len()).collect::<Set<_>>();
+// tag::odds[]
+type Word = u64;
+const WORD_BITS: usize = Word::BITS as usize;
 
-	for &num in &game.numbers {
-		for (board_idx, board) in game.boards.iter_mut().enumerate() {
-			let already_won = !ongoing_game_idxs.contains(&board_idx);
-			if already_won {
-				continue;
-			}
+/// A board as bitmasks of `n_words` words each, enough for any size of board: bit
+/// `r * n_cols + c` stands for cell `(r, c)`. Each drawable number has been given an id,
+/// and `cells_by_num_id[id]` is the `(word_idx, bit)` of the cell with that number (with
+/// a `bit` of 0 if there isn't one)
+#[derive(Debug)]
+struct FastBoard {
+	n_words: usize,
+	cells_by_num_id: Vec<(usize, Word)>,
+	/// The words of each pattern in turn
+	patterns: Vec<Word>,
+}
 
-			board.play_number(num);
-			if board.has_won() {
-				if ongoing_game_idxs.len() == 1 {
-					return board.get_ans(num);
+impl<T: Integer + Copy> Game<T> {
+	/// Gives each distinct drawn number an id and converts each board to bitmasks
+	fn fast_boards(&self, rules: &WinRules) -> (Vec<usize>, Vec<FastBoard>) {
+		let mut ids = Map::new();
+		let num_ids = self
+			.numbers
+			.iter()
+			.map(|&n| {
+				let next_id = ids.len();
+				*ids.entry(n).or_insert(next_id)
+			})
+			.collect::<Vec<_>>();
+
+		let boards = self
+			.boards
+			.iter()
+			.map(|board| {
+				let n_words = ((board.n_rows * board.n_cols + WORD_BITS - 1) / WORD_BITS).max(1);
+				let bit = |(r, c): (usize, usize)| {
+					let idx = r * board.n_cols + c;
+					(idx / WORD_BITS, 1 << (idx % WORD_BITS))
+				};
+
+				let mut cells_by_num_id = vec![(0, 0); ids.len()];
+				for (n, &cell) in &board.grid {
+					if let Some(&id) = ids.get(n) {
+						cells_by_num_id[id] = bit(cell);
+					}
+				}
+				let mut patterns = Vec::new();
+				for (_, cells) in rules.patterns(board.n_rows, board.n_cols) {
+					let start = patterns.len();
+					patterns.resize(start + n_words, 0);
+					for (word_idx, b) in cells.into_iter().map(bit) {
+						patterns[start + word_idx] |= b;
+					}
+				}
+
+				FastBoard {
+					n_words,
+					cells_by_num_id,
+					patterns,
 				}
+			})
+			.collect();
+
+		(num_ids, boards)
+	}
+}
+
+/// Plays the numbers with ids `num_ids` in order and returns the `(board_idx, turn)` of
+/// each win, in order. `marked` is scratch space, one entry per board
+fn fast_wins(
+	boards: &[FastBoard],
+	num_ids: &[usize],
+	marked: &mut [Vec<Word>],
+) -> Vec<(usize, usize)> {
+	for (m, board) in marked.iter_mut().zip(boards) {
+		m.clear();
+		m.resize(board.n_words, 0);
+	}
+	let mut has_won = vec![false; boards.len()];
+	let mut wins = Vec::new();
 
-				ongoing_game_idxs.remove(&board_idx);
+	for (turn, &id) in num_ids.iter().enumerate() {
+		if wins.len() == boards.len() {
+			break;
+		}
+		for (board_idx, board) in boards.iter().enumerate() {
+			let (word_idx, cell) = board.cells_by_num_id[id];
+			if has_won[board_idx] || cell == 0 {
+				continue;
 			}
+			let m = &mut marked[board_idx];
+			m[word_idx] |= cell;
+			if board.patterns.chunks_exact(board.n_words).any(|p| {
+				p[word_idx] & cell != 0 && p.iter().zip(m.iter()).all(|(&p, &m)| p & m == p)
+			}) {
+				has_won[board_idx] = true;
+				wins.push((board_idx, turn));
+			}
+		}
+	}
+
+	wins
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OddsConfig {
+	pub n_trials: u64,
+	pub seed: u64,
+	pub n_threads: usize,
+}
+
+/// An estimate with a 95% confidence interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+	pub value: f64,
+	pub low: f64,
+	pub high: f64,
+}
+
+const Z_95: f64 = 1.96;
+
+// Counts are far below 2^52, so converting them to `f64` is exact
+#[allow(clippy::cast_precision_loss)]
+impl Estimate {
+	/// Wilson score interval for a proportion; `n_trials` must not be 0
+	fn proportion(n_successes: u64, n_trials: u64) -> Self {
+		let n = n_trials as f64;
+		let p = n_successes as f64 / n;
+		let z2 = Z_95 * Z_95;
+		let denom = 1.0 + z2 / n;
+		let center = (p + z2 / (2.0 * n)) / denom;
+		let half_width = Z_95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / denom;
+		Self {
+			value: p,
+			low: (center - half_width).max(0.0),
+			high: (center + half_width).min(1.0),
 		}
 	}
-	unreachable!();
+
+	/// Normal approximation for a mean, given the sum and sum of squares of the samples
+	fn mean(n: u64, sum: u64, sum_sq: u64) -> Option<Self> {
+		if n == 0 {
+			return None;
+		}
+		let n_f = n as f64;
+		let mean = sum as f64 / n_f;
+		let variance = if n > 1 {
+			((sum_sq as f64 - n_f * mean * mean) / (n_f - 1.0)).max(0.0)
+		} else {
+			0.0
+		};
+		let half_width = Z_95 * (variance / n_f).sqrt();
+		Some(Self {
+			value: mean,
+			low: mean - half_width,
+			high: mean + half_width,
+		})
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardOdds {
+	pub p_first: Estimate,
+	pub p_last: Estimate,
+	pub p_never: Estimate,
+	/// The (0-indexed) turn on which the board wins, given that it wins at all. `None` if
+	/// it never won in any trial
+	pub expected_turn: Option<Estimate>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct OddsTally {
+	n_first: u64,
+	n_last: u64,
+	n_wins: u64,
+	turn_sum: u64,
+	turn_sq_sum: u64,
+}
+
+impl<T: Integer + Copy> Game<T> {
+	/// Estimates each board's odds by playing `config.n_trials` games, each with the
+	/// numbers drawn in a random order. Trial `i` is seeded from `(config.seed, i)`
+	/// alone, so the results don't depend on the number of threads. `None` if
+	/// `config.n_trials` is 0, as there's nothing to estimate from
+	pub fn odds(&self, rules: &WinRules, config: &OddsConfig) -> Option<Vec<BoardOdds>> {
+		if config.n_trials == 0 {
+			return None;
+		}
+
+		let (num_ids, boards) = self.fast_boards(rules);
+		let n_boards = boards.len();
+		let n_threads = config.n_threads.max(1) as u64;
+		let trials_per_thread = (config.n_trials + n_threads - 1) / n_threads;
+
+		let tallies = std::thread::scope(|scope| {
+			let handles = (0..n_threads)
+				.map(|thread_idx| {
+					let (boards, num_ids) = (&boards, &num_ids);
+					let start = (thread_idx * trials_per_thread).min(config.n_trials);
+					let end = (start + trials_per_thread).min(config.n_trials);
+
+					scope.spawn(move || {
+						let mut tallies = vec![OddsTally::default(); n_boards];
+						let mut order = num_ids.clone();
+						let mut marked = vec![Vec::new(); n_boards];
+
+						for trial in start..end {
+							order.copy_from_slice(num_ids);
+							SplitMix64::for_stream(config.seed, trial).shuffle(&mut order);
+
+							let wins = fast_wins(boards, &order, &mut marked);
+							if let Some(&(board_idx, _)) = wins.first() {
+								tallies[board_idx].n_first += 1;
+							}
+							if let Some(&(board_idx, _)) = wins.last() {
+								tallies[board_idx].n_last += 1;
+							}
+							for (board_idx, turn) in wins {
+								let turn = turn as u64;
+								let tally = &mut tallies[board_idx];
+								tally.n_wins += 1;
+								tally.turn_sum += turn;
+								tally.turn_sq_sum += turn * turn;
+							}
+						}
+						tallies
+					})
+				})
+				.collect::<Vec<_>>();
+
+			handles
+				.into_iter()
+				.map(|h| h.join().unwrap())
+				.reduce(|mut acc, tallies| {
+					for (a, t) in acc.iter_mut().zip(tallies) {
+						a.n_first += t.n_first;
+						a.n_last += t.n_last;
+						a.n_wins += t.n_wins;
+						a.turn_sum += t.turn_sum;
+						a.turn_sq_sum += t.turn_sq_sum;
+					}
+					acc
+				})
+				.unwrap_or_else(|| vec![OddsTally::default(); n_boards])
+		});
+
+		let n_trials = config.n_trials;
+		Some(
+			tallies
+				.into_iter()
+				.map(|t| BoardOdds {
+					p_first: Estimate::proportion(t.n_first, n_trials),
+					p_last: Estimate::proportion(t.n_last, n_trials),
+					p_never: Estimate::proportion(n_trials - t.n_wins, n_trials),
+					expected_turn: Estimate::mean(t.n_wins, t.turn_sum, t.turn_sq_sum),
+				})
+				.collect(),
+		)
+	}
+}
+// end::odds[]
+
+// tag::pt1[]
+fn pt1(tournament: &Tournament<i32>) -> Option<i32> {
+	tournament.wins.first().map(|win| win.score)
+}
+// end::pt1[]
+
+// tag::pt2[]
+fn pt2(tournament: &Tournament<i32>) -> Option<i32> {
+	tournament.wins.last().map(|win| win.score)
 }
 // end::pt2[]
 
@@ -181,4 +618,237 @@ mod test {
 		test_input!(include_str!("sample_input.txt"), day: 4, ans: (4512, 1924));
 		test_input!(include_str!("input.txt"), day: 4, ans: (87456, 15561));
 	}
+
+	#[test]
+	fn test_tournament() {
+		let tournament = Game::<i32>::parse(include_str!("sample_input.txt"))
+			.unwrap()
+			.play_tournament();
+		assert_eq!(
+			tournament.wins,
+			vec![
+				Win {
+					board_idx: 2,
+					turn: 11,
+					winning_num: 24,
+					pattern: WinPattern::Row(0),
+					score: 4512,
+				},
+				Win {
+					board_idx: 0,
+					turn: 13,
+					winning_num: 16,
+					pattern: WinPattern::Row(2),
+					score: 2192,
+				},
+				Win {
+					board_idx: 1,
+					turn: 14,
+					winning_num: 13,
+					pattern: WinPattern::Col(2),
+					score: 1924,
+				},
+			]
+		);
+		assert!(tournament.never_won.is_empty());
+
+		// Drawing only the first 12 numbers, only the third board wins
+		let (numbers, boards) = include_str!("sample_input.txt").split_once('\n').unwrap();
+		let numbers = numbers.split(',').take(12).collect::<Vec<_>>().join(",");
+		let tournament = Game::<i32>::parse(&format!("{numbers}\n{boards}"))
+			.unwrap()
+			.play_tournament();
+		assert_eq!(tournament.wins.len(), 1);
+		assert_eq!(tournament.never_won, vec![0, 1]);
+		assert_eq!(pt2(&tournament), Some(4512));
+	}
+
+	#[test]
+	fn test_patterns() {
+		fn first_win(input: &str, rules: &WinRules) -> Option<Win<i32>> {
+			Game::<i32>::parse(input)
+				.unwrap()
+				.with_rules(rules)
+				.play_tournament()
+				.wins
+				.first()
+				.copied()
+		}
+
+		// A 2x4 board
+		let input = "1,8,5,4,6,2,7,3\n\n1 2 3 4\n5 6 7 8\n";
+		let only = |rules: WinRules| WinRules {
+			rows: false,
+			cols: false,
+			..rules
+		};
+
+		let win = first_win(input, &WinRules::default()).unwrap();
+		assert_eq!((win.turn, win.pattern), (2, WinPattern::Col(0)));
+
+		let rules = only(WinRules {
+			diagonals: true,
+			..WinRules::default()
+		});
+		let win = first_win(input, &rules).unwrap();
+		assert_eq!((win.turn, win.pattern), (4, WinPattern::Diagonal));
+
+		let rules = only(WinRules {
+			corners: true,
+			..WinRules::default()
+		});
+		let win = first_win(input, &rules).unwrap();
+		assert_eq!((win.turn, win.pattern), (3, WinPattern::Corners));
+		assert_eq!(win.score, 4 * (2 + 3 + 6 + 7));
+
+		let rules = only(WinRules {
+			blackout: true,
+			..WinRules::default()
+		});
+		let win = first_win(input, &rules).unwrap();
+		assert_eq!(
+			(win.turn, win.pattern, win.score),
+			(7, WinPattern::Blackout, 0)
+		);
+
+		// The first mask doesn't fit on the board, so it's ignored
+		let rules = only(WinRules {
+			masks: vec![vec![(2, 0)], vec![(0, 1), (1, 2)]],
+			..WinRules::default()
+		});
+		let win = first_win(input, &rules).unwrap();
+		assert_eq!((win.turn, win.pattern), (6, WinPattern::Mask(1)));
+
+		assert_eq!(first_win(input, &only(WinRules::default())), None);
+	}
+
+	#[test]
+	fn test_odds() {
+		let input = include_str!("sample_input.txt");
+		let game = Game::<i32>::parse(input).unwrap();
+		let rules = WinRules::default();
+
+		// The bitmask boards agree with the real ones on shuffled draws
+		let (num_ids, boards) = game.fast_boards(&rules);
+		let mut marked = vec![Vec::new(); boards.len()];
+		for trial in 0..20 {
+			let mut order = (0..game.numbers.len()).collect::<Vec<_>>();
+			SplitMix64::for_stream(1, trial).shuffle(&mut order);
+
+			let shuffled = Game {
+				boards: Game::<i32>::parse(input).unwrap().boards,
+				numbers: order.iter().map(|&i| game.numbers[i]).collect(),
+			};
+			let expected = shuffled
+				.play_tournament()
+				.wins
+				.iter()
+				.map(|w| (w.board_idx, w.turn))
+				.collect::<Vec<_>>();
+			let order_ids = order.iter().map(|&i| num_ids[i]).collect::<Vec<_>>();
+			assert_eq!(fast_wins(&boards, &order_ids, &mut marked), expected);
+		}
+
+		let config = OddsConfig {
+			n_trials: 2000,
+			seed: 2021,
+			n_threads: 4,
+		};
+		let odds = game.odds(&rules, &config).unwrap();
+		assert_eq!(odds.len(), 3);
+		assert_eq!(
+			Some(odds.clone()),
+			game.odds(
+				&rules,
+				&OddsConfig {
+					n_threads: 3,
+					..config
+				}
+			)
+		);
+
+		let total_first = odds.iter().map(|o| o.p_first.value).sum::<f64>();
+		let total_last = odds.iter().map(|o| o.p_last.value).sum::<f64>();
+		assert!((total_first - 1.0).abs() < 1e-9);
+		assert!((total_last - 1.0).abs() < 1e-9);
+		for o in &odds {
+			assert!(o.p_first.low <= o.p_first.value && o.p_first.value <= o.p_first.high);
+			assert!(o.p_never.value.abs() < f64::EPSILON);
+			let turn = o.expected_turn.unwrap();
+			assert!(turn.low <= turn.value && turn.value <= turn.high);
+			// All 27 numbers are drawn, so every board eventually wins
+			assert!((4.0..27.0).contains(&turn.value));
+		}
+	}
+
+	#[test]
+	fn test_odds_large_boards() {
+		// 144 cells don't fit in a single `u128`; the second board is the first transposed
+		let size = 12;
+		let board = |cell: &dyn Fn(usize, usize) -> usize| {
+			(0..size)
+				.map(|r| {
+					(0..size)
+						.map(|c| cell(r, c).to_string())
+						.collect::<Vec<_>>()
+						.join(" ")
+				})
+				.collect::<Vec<_>>()
+				.join("\n")
+		};
+		let numbers = (0..size * size)
+			.map(|n| n.to_string())
+			.collect::<Vec<_>>()
+			.join(",");
+		let input = format!(
+			"{numbers}\n\n{}\n\n{}",
+			board(&|r, c| r * size + c),
+			board(&|r, c| c * size + r)
+		);
+		let game = Game::<i32>::parse(&input).unwrap();
+		let rules = WinRules::default();
+
+		let (num_ids, boards) = game.fast_boards(&rules);
+		assert!(boards.iter().all(|b| b.n_words == 3));
+		let mut marked = vec![Vec::new(); boards.len()];
+		for trial in 0..20 {
+			let mut order = (0..game.numbers.len()).collect::<Vec<_>>();
+			SplitMix64::for_stream(2, trial).shuffle(&mut order);
+
+			let shuffled = Game {
+				boards: Game::<i32>::parse(&input).unwrap().boards,
+				numbers: order.iter().map(|&i| game.numbers[i]).collect(),
+			};
+			let expected = shuffled
+				.play_tournament()
+				.wins
+				.iter()
+				.map(|w| (w.board_idx, w.turn))
+				.collect::<Vec<_>>();
+			let order_ids = order.iter().map(|&i| num_ids[i]).collect::<Vec<_>>();
+			assert_eq!(fast_wins(&boards, &order_ids, &mut marked), expected);
+		}
+
+		let config = OddsConfig {
+			n_trials: 200,
+			seed: 2021,
+			n_threads: 2,
+		};
+		let odds = game.odds(&rules, &config).unwrap();
+		assert_eq!(odds.len(), 2);
+		for o in &odds {
+			assert!(o.p_never.value.abs() < f64::EPSILON);
+		}
+
+		assert_eq!(
+			game.odds(
+				&rules,
+				&OddsConfig {
+					n_trials: 0,
+					..config
+				}
+			),
+			None
+		);
+	}
 }
@@ -1,15 +1,17 @@
 // tag::setup[]
 use crate::Answer;
+use hashbrown::{HashMap, HashSet};
 use num::Integer;
 use regex::Regex;
 use std::{collections::BTreeMap as Map, str::FromStr};
 
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
-struct Point<T>(T, T);
-struct EndpointPair<T: Integer>(Point<T>, Point<T>);
-type PointCounter<T> = Map<Point<T>, usize>;
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Point<T>(pub T, pub T);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndpointPair<T: Integer>(pub Point<T>, pub Point<T>);
+pub type PointCounter<T> = Map<Point<T>, usize>;
 
-fn get_lines<T: Integer + FromStr>(input: &str) -> Option<Vec<EndpointPair<T>>> {
+pub fn get_lines<T: Integer + FromStr>(input: &str) -> Option<Vec<EndpointPair<T>>> {
 	let line_re = Regex::new(r"(\d+),(\d+)\s*->\s*(\d+),(\d+)").ok()?;
 	input
 		.lines()
@@ -21,9 +23,55 @@ fn get_lines<T: Integer + FromStr>(input: &str) -> Option<Vec<EndpointPair<T>>>
 		.collect::<Option<Vec<_>>>()
 }
 
-fn range_between(a: i32, b: i32) -> num::iter::RangeStepInclusive<i32> {
-	let step = if a < b { 1 } else { -1 };
-	num::range_step_inclusive(a, b, step)
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LineClass {
+	/// Includes degenerate lines whose endpoints are equal
+	Horizontal,
+	Vertical,
+	/// Slope ±1
+	Diagonal,
+	/// Any other slope
+	Oblique,
+}
+
+impl EndpointPair<i32> {
+	pub fn class(&self) -> LineClass {
+		let &EndpointPair(Point(x1, y1), Point(x2, y2)) = self;
+		let (dx, dy) = (x2 - x1, y2 - y1);
+		if dy == 0 {
+			LineClass::Horizontal
+		} else if dx == 0 {
+			LineClass::Vertical
+		} else if dx.abs() == dy.abs() {
+			LineClass::Diagonal
+		} else {
+			LineClass::Oblique
+		}
+	}
+
+	/// Every point with integer coordinates on the line, from the first endpoint to the
+	/// second. With `g = gcd(dx, dy)`, consecutive such points are `(dx/g, dy/g)` apart
+	pub fn lattice_points(&self) -> impl Iterator<Item = Point<i32>> {
+		let &EndpointPair(Point(x1, y1), Point(x2, y2)) = self;
+		let (dx, dy) = (x2 - x1, y2 - y1);
+		let g = num::integer::gcd(dx, dy);
+		let (step_x, step_y) = if g == 0 { (0, 0) } else { (dx / g, dy / g) };
+		(0..=g).map(move |k| Point(x1 + k * step_x, y1 + k * step_y))
+	}
+}
+
+/// Counts the points covered by the lines whose class satisfies `include_class`
+pub fn get_point_counts(
+	endpoints: &[EndpointPair<i32>],
+	include_class: impl Fn(LineClass) -> bool,
+) -> PointCounter<i32> {
+	let mut counter = Map::new();
+	for line in endpoints.iter().filter(|line| include_class(line.class())) {
+		for point in line.lattice_points() {
+			*counter.entry(point).or_default() += 1;
+		}
+	}
+	counter
 }
 
 fn get_ans<T>(counter: &PointCounter<T>) -> usize {
@@ -45,57 +93,357 @@ pub fn ans() -> Answer<usize, usize> {
 // end::setup[]
 
 // tag::pt1[]
-fn get_hv_point_counts(endpoints: &[EndpointPair<i32>]) -> PointCounter<i32> {
-	let mut counter = Map::new();
-	for &EndpointPair(Point(x1, y1), Point(x2, y2)) in endpoints {
-		if x1 != x2 && y1 != y2 {
-			continue;
+fn pt1(endpoints: &[EndpointPair<i32>]) -> usize {
+	get_ans(&get_point_counts(endpoints, |c| {
+		matches!(c, LineClass::Horizontal | LineClass::Vertical)
+	}))
+}
+// end::pt1[]
+
+// tag::pt2[]
+fn pt2(endpoints: &[EndpointPair<i32>]) -> usize {
+	get_ans(&get_point_counts(endpoints, |c| c != LineClass::Oblique))
+}
+// end::pt2[]
+
+// tag::any_slope[]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverlapReport {
+	/// For each class, the number of points covered by at least two lines of that class
+	pub by_class: Map<LineClass, usize>,
+	/// The number of points covered by at least two lines of any classes
+	pub all: usize,
+}
+
+pub fn overlaps_by_class(endpoints: &[EndpointPair<i32>]) -> OverlapReport {
+	let by_class = [
+		LineClass::Horizontal,
+		LineClass::Vertical,
+		LineClass::Diagonal,
+		LineClass::Oblique,
+	]
+	.into_iter()
+	.map(|class| (class, get_ans(&get_point_counts(endpoints, |c| c == class))))
+	.collect();
+
+	OverlapReport {
+		by_class,
+		all: get_ans(&get_point_counts(endpoints, |_| true)),
+	}
+}
+// end::any_slope[]
+
+// tag::sweep[]
+/// A segment on the line `{p : b * p.x - a * p.y = c}`, whose lattice points are exactly
+/// `base + t * (a, b)` for integer `t`; the segment is the lattice points with `t` in
+/// `t_lo..=t_hi`. `(a, b)` is primitive, and is `(1, 0)` for a degenerate segment. `c` and
+/// `t` are products of coordinates, so they're kept as `i128`s
+#[derive(Debug, Clone, Copy)]
+struct ParamSegment {
+	line: (i128, i128, i128),
+	t_lo: i128,
+	t_hi: i128,
+	from: (i64, i64),
+	delta: (i64, i64),
+}
+
+impl ParamSegment {
+	fn new(&EndpointPair(Point(x1, y1), Point(x2, y2)): &EndpointPair<i64>) -> Self {
+		let (dx, dy) = (x2 - x1, y2 - y1);
+		let g = num::integer::gcd(dx, dy);
+		let (a, b) = if g == 0 {
+			(1, 0)
+		} else if dx < 0 || (dx == 0 && dy < 0) {
+			(-dx / g, -dy / g)
+		} else {
+			(dx / g, dy / g)
+		};
+		let (a, b) = (i128::from(a), i128::from(b));
+		let line = (a, b, b * i128::from(x1) - a * i128::from(y1));
+		let [t1, t2] = [(x1, y1), (x2, y2)].map(|p| Self::param(line, p));
+		Self {
+			line,
+			t_lo: t1.min(t2),
+			t_hi: t1.max(t2),
+			from: (x1, y1),
+			delta: (dx, dy),
+		}
+	}
+
+	/// The `t` of lattice point `(x, y)` on `line`. Along the line, `a * x + b * y` goes
+	/// up by exactly `a^2 + b^2` per lattice point
+	fn param((a, b, _): (i128, i128, i128), (x, y): (i64, i64)) -> i128 {
+		(a * i128::from(x) + b * i128::from(y)).div_euclid(a * a + b * b)
+	}
+
+	fn x_range(&self) -> (i64, i64) {
+		let x2 = self.from.0 + self.delta.0;
+		(self.from.0.min(x2), self.from.0.max(x2))
+	}
+
+	fn y_range(&self) -> (i64, i64) {
+		let y2 = self.from.1 + self.delta.1;
+		(self.from.1.min(y2), self.from.1.max(y2))
+	}
+
+	/// The lattice point where two segments on different lines cross, if any
+	fn lattice_intersection(&self, other: &Self) -> Option<(i64, i64)> {
+		fn cross((x1, y1): (i128, i128), (x2, y2): (i128, i128)) -> i128 {
+			x1 * y2 - y1 * x2
 		}
-		for x in range_between(x1, x2) {
-			for y in range_between(y1, y2) {
-				*counter.entry(Point(x, y)).or_default() += 1;
+		let wide = |(x, y): (i64, i64)| (i128::from(x), i128::from(y));
+
+		let (start, dir) = (wide(self.from), wide(self.delta));
+		let (o_start, o_dir) = (wide(other.from), wide(other.delta));
+		let between = (o_start.0 - start.0, o_start.1 - start.1);
+
+		let denom = cross(dir, o_dir);
+		let (x, y) = if denom == 0 {
+			// Segments on different lines can only meet here if one is a single point
+			let (point, seg_start, seg_dir) = if dir == (0, 0) {
+				(start, o_start, o_dir)
+			} else {
+				(o_start, start, dir)
+			};
+			let rel = (point.0 - seg_start.0, point.1 - seg_start.1);
+			if cross(rel, seg_dir) != 0 {
+				return None;
+			}
+			point
+		} else {
+			// start + u * dir == o_start + v * o_dir, with u = u_num / denom and
+			// v = v_num / denom
+			let (mut u_num, mut v_num, mut denom) =
+				(cross(between, o_dir), cross(between, dir), denom);
+			if denom < 0 {
+				(u_num, v_num, denom) = (-u_num, -v_num, -denom);
+			}
+			if !(0..=denom).contains(&u_num) || !(0..=denom).contains(&v_num) {
+				return None;
 			}
+			let (x_num, y_num) = (dir.0 * u_num, dir.1 * u_num);
+			if x_num % denom != 0 || y_num % denom != 0 {
+				return None;
+			}
+			(start.0 + x_num / denom, start.1 + y_num / denom)
+		};
+
+		let point = (i64::try_from(x).ok()?, i64::try_from(y).ok()?);
+		let contains = |s: &Self| {
+			let ((x_lo, x_hi), (y_lo, y_hi)) = (s.x_range(), s.y_range());
+			(x_lo..=x_hi).contains(&point.0) && (y_lo..=y_hi).contains(&point.1)
+		};
+		(contains(self) && contains(other)).then_some(point)
+	}
+}
+
+/// The coverage of one line, as runs `(t_start, coverage)`: the coverage is constant from
+/// `t_start` up to the next run's `t_start`
+fn coverage_runs(segments: &[ParamSegment]) -> Vec<(i128, usize)> {
+	let mut events = segments
+		.iter()
+		.flat_map(|s| [(s.t_lo, 1_isize), (s.t_hi + 1, -1)])
+		.collect::<Vec<_>>();
+	events.sort_unstable();
+
+	let mut runs = Vec::<(i128, usize)>::new();
+	let mut coverage = 0_isize;
+	for (t, delta) in events {
+		coverage += delta;
+		let coverage = usize::try_from(coverage).unwrap();
+		match runs.last_mut() {
+			Some(last) if last.0 == t => last.1 = coverage,
+			_ => runs.push((t, coverage)),
 		}
 	}
-	counter
+	runs
 }
 
-fn pt1(endpoints: &[EndpointPair<i32>]) -> usize {
-	get_ans(&get_hv_point_counts(endpoints))
+fn coverage_at(runs: &[(i128, usize)], t: i128) -> usize {
+	match runs.partition_point(|&(start, _)| start <= t) {
+		0 => 0,
+		i => runs[i - 1].1,
+	}
 }
-// end::pt1[]
 
-// tag::pt2[]
-fn get_diag_point_counts(endpoints: &[EndpointPair<i32>]) -> PointCounter<i32> {
-	let mut counter = Map::new();
-	for &EndpointPair(Point(x1, y1), Point(x2, y2)) in endpoints {
-		if (x1 - x2).abs() != (y1 - y2).abs() {
-			continue;
+/// Counts the lattice points covered by at least `k` (>= 1) segments, without visiting
+/// each covered point. Collinear segments are merged by sweeping along their common line,
+/// and the remaining overlaps, which are single points where different lines cross, are
+/// found by sweeping a vertical line across the plane and intersecting the segments whose
+/// x-ranges overlap.
+///
+/// Each segment's `x2 - x1` and `y2 - y1` must fit in an `i64`, as they always do for
+/// nonnegative coordinates
+pub fn count_overlaps_sweep(endpoints: &[EndpointPair<i64>], k: usize) -> usize {
+	assert!(k >= 1);
+
+	let segments = endpoints.iter().map(ParamSegment::new).collect::<Vec<_>>();
+
+	let mut lines = HashMap::<_, Vec<_>>::new();
+	for seg in &segments {
+		lines.entry(seg.line).or_default().push(*seg);
+	}
+	let runs_by_line = lines
+		.iter()
+		.map(|(&line, segs)| (line, coverage_runs(segs)))
+		.collect::<HashMap<_, _>>();
+
+	// Points where different lines cross, and the lines crossing there
+	let mut crossings = HashMap::<(i64, i64), HashSet<(i128, i128, i128)>>::new();
+	let mut by_x_lo = (0..segments.len()).collect::<Vec<_>>();
+	by_x_lo.sort_unstable_by_key(|&i| segments[i].x_range().0);
+	let mut active = Vec::<usize>::new();
+	for i in by_x_lo {
+		let seg = &segments[i];
+		let (x_lo, _) = seg.x_range();
+		active.retain(|&j| segments[j].x_range().1 >= x_lo);
+
+		for &j in &active {
+			let other = &segments[j];
+			if other.line == seg.line {
+				continue;
+			}
+			let ((y_lo, y_hi), (o_y_lo, o_y_hi)) = (seg.y_range(), other.y_range());
+			if y_hi < o_y_lo || o_y_hi < y_lo {
+				continue;
+			}
+			if let Some(point) = seg.lattice_intersection(other) {
+				let lines_here = crossings.entry(point).or_default();
+				lines_here.insert(seg.line);
+				lines_here.insert(other.line);
+			}
+		}
+		active.push(i);
+	}
+
+	let mut n_points = 0;
+
+	// Count points line by line...
+	for runs in runs_by_line.values() {
+		for window in runs.windows(2) {
+			let [(start, coverage), (end, _)] = [window[0], window[1]];
+			if coverage >= k {
+				n_points += usize::try_from(end - start).unwrap();
+			}
 		}
+	}
 
-		for (x, y) in range_between(x1, x2).zip(range_between(y1, y2)) {
-			*counter.entry(Point(x, y)).or_default() += 1;
+	// ...except that crossings were counted once per line through them, using only that
+	// line's coverage. So replace those counts with one based on their total coverage
+	for (&point, lines_here) in &crossings {
+		let coverages = lines_here
+			.iter()
+			.map(|line| coverage_at(&runs_by_line[line], ParamSegment::param(*line, point)))
+			.collect::<Vec<_>>();
+		n_points -= coverages.iter().filter(|&&c| c >= k).count();
+		if coverages.iter().sum::<usize>() >= k {
+			n_points += 1;
 		}
 	}
 
+	n_points
+}
+// end::sweep[]
+
+// tag::export[]
+pub fn count_at(counter: &PointCounter<i32>, point: Point<i32>) -> usize {
+	counter.get(&point).copied().unwrap_or(0)
+}
+
+/// The points covered at least `k` times, with their counts, in order
+pub fn points_with_count_at_least(
+	counter: &PointCounter<i32>,
+	k: usize,
+) -> impl Iterator<Item = (Point<i32>, usize)> + '_ {
 	counter
+		.iter()
+		.filter_map(move |(&point, &count)| (count >= k).then_some((point, count)))
 }
 
-fn pt2(endpoints: &[EndpointPair<i32>]) -> usize {
-	let hv_counter = get_hv_point_counts(endpoints);
+/// Writes `x,y,count` rows (after a header) for the points covered at least `min_count`
+/// times
+///
+/// # Errors
+///
+/// Any error from writing to `out`
+pub fn write_csv(
+	counter: &PointCounter<i32>,
+	min_count: usize,
+	out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+	writeln!(out, "x,y,count")?;
+	for (Point(x, y), count) in points_with_count_at_least(counter, min_count) {
+		writeln!(out, "{x},{y},{count}")?;
+	}
+	Ok(())
+}
 
-	let all_counter = {
-		let mut diag_counter = get_diag_point_counts(endpoints);
+/// Each point's count scaled to `0..=255` relative to the largest count, in row-major
+/// order (`y` down, `x` across) over the bounding box of all counted points. Points
+/// covered fewer than `min_count` times are 0
+fn heat_levels(counter: &PointCounter<i32>, min_count: usize) -> (usize, usize, Vec<u8>) {
+	if counter.is_empty() {
+		return (0, 0, Vec::new());
+	}
+	let xs = || counter.keys().map(|&Point(x, _)| x);
+	let ys = || counter.keys().map(|&Point(_, y)| y);
+	let (x_lo, x_hi) = (xs().min().unwrap(), xs().max().unwrap());
+	let (y_lo, y_hi) = (ys().min().unwrap(), ys().max().unwrap());
 
-		for (k, v) in hv_counter {
-			*diag_counter.entry(k).or_default() += v;
-		}
+	let width = usize::try_from(x_hi - x_lo + 1).unwrap();
+	let height = usize::try_from(y_hi - y_lo + 1).unwrap();
+	let max_count = counter.values().copied().max().unwrap_or(0).max(1);
 
-		diag_counter
-	};
-	get_ans(&all_counter)
+	let mut levels = vec![0; width * height];
+	for (Point(x, y), count) in points_with_count_at_least(counter, min_count) {
+		let col = usize::try_from(x - x_lo).unwrap();
+		let row = usize::try_from(y - y_lo).unwrap();
+		levels[row * width + col] = u8::try_from(count * 255 / max_count).unwrap();
+	}
+
+	(width, height, levels)
 }
-// end::pt2[]
+
+/// Writes a grayscale heatmap as a binary PGM image
+///
+/// # Errors
+///
+/// Any error from writing to `out`
+pub fn write_pgm(
+	counter: &PointCounter<i32>,
+	min_count: usize,
+	out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+	let (width, height, levels) = heat_levels(counter, min_count);
+	write!(out, "P5\n{width} {height}\n255\n")?;
+	out.write_all(&levels)
+}
+
+/// Writes a heatmap as a binary PPM image, going from black through red and yellow to
+/// white as the count increases
+///
+/// # Errors
+///
+/// Any error from writing to `out`
+pub fn write_ppm(
+	counter: &PointCounter<i32>,
+	min_count: usize,
+	out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+	fn color(level: u8) -> [u8; 3] {
+		let level = u16::from(level) * 3;
+		let channel = |offset: u16| u8::try_from(level.saturating_sub(offset).min(255)).unwrap();
+		[channel(0), channel(255), channel(510)]
+	}
+
+	let (width, height, levels) = heat_levels(counter, min_count);
+	write!(out, "P6\n{width} {height}\n255\n")?;
+	for level in levels {
+		out.write_all(&color(level))?;
+	}
+	Ok(())
+}
+// end::export[]
 
 #[cfg(test)]
 mod test {
@@ -107,4 +455,158 @@ mod test {
 		test_input!(include_str!("sample_input.txt"), day: 5, ans: (5, 12));
 		test_input!(include_str!("input.txt"), day: 5, ans: (5576, 18144));
 	}
+
+	#[test]
+	fn test_any_slope() {
+		use LineClass::*;
+
+		let endpoints = get_lines(include_str!("input.txt")).unwrap();
+		assert_eq!(
+			get_ans(&get_point_counts(&endpoints, |c| matches!(
+				c,
+				Horizontal | Vertical
+			))),
+			5576
+		);
+		assert_eq!(
+			get_ans(&get_point_counts(&endpoints, |c| c != Oblique)),
+			18144
+		);
+
+		let line = EndpointPair(Point(7, 1), Point(1, 10));
+		assert_eq!(line.class(), Oblique);
+		assert_eq!(
+			line.lattice_points().collect::<Vec<_>>(),
+			vec![Point(7, 1), Point(5, 4), Point(3, 7), Point(1, 10)]
+		);
+		assert_eq!(
+			EndpointPair(Point(3, 3), Point(3, 3))
+				.lattice_points()
+				.collect::<Vec<_>>(),
+			vec![Point(3, 3)]
+		);
+
+		let endpoints = get_lines(
+			"0,0 -> 4,2
+			4,0 -> 0,2
+			2,0 -> 2,4
+			0,1 -> 4,1
+			0,0 -> 2,2
+			2,0 -> 0,2
+			0,2 -> 6,5",
+		)
+		.unwrap();
+		let report = overlaps_by_class(&endpoints);
+		assert_eq!(
+			report.by_class,
+			[(Horizontal, 0), (Vertical, 0), (Diagonal, 1), (Oblique, 2)]
+				.into_iter()
+				.collect()
+		);
+		assert_eq!(report.all, 7);
+	}
+
+	#[test]
+	fn test_sweep() {
+		use crate::utils::SplitMix64;
+
+		fn count_overlaps_map(endpoints: &[EndpointPair<i32>], k: usize) -> usize {
+			get_point_counts(endpoints, |_| true)
+				.values()
+				.filter(|&&count| count >= k)
+				.count()
+		}
+		fn widen(endpoints: &[EndpointPair<i32>]) -> Vec<EndpointPair<i64>> {
+			endpoints
+				.iter()
+				.map(|&EndpointPair(Point(x1, y1), Point(x2, y2))| {
+					EndpointPair(Point(x1.into(), y1.into()), Point(x2.into(), y2.into()))
+				})
+				.collect()
+		}
+
+		let endpoints = get_lines::<i32>(include_str!("input.txt")).unwrap();
+		assert_eq!(count_overlaps_sweep(&widen(&endpoints), 2), 18144);
+
+		let mut rng = SplitMix64::new(5);
+		for _ in 0..50 {
+			let mut coord = || i32::try_from(rng.below(12)).unwrap();
+			let endpoints = (0..25)
+				.map(|_| EndpointPair(Point(coord(), coord()), Point(coord(), coord())))
+				.collect::<Vec<_>>();
+			let wide = widen(&endpoints);
+			for k in 1..=4 {
+				assert_eq!(
+					count_overlaps_sweep(&wide, k),
+					count_overlaps_map(&endpoints, k)
+				);
+			}
+		}
+
+		// Far too many points to visit one by one
+		let endpoints = get_lines::<i64>(
+			"0,0 -> 2000000000,2000000000
+			1000000000,1000000000 -> 3000000000,3000000000
+			0,2000000000 -> 2000000000,0
+			0,500000000 -> 3000000000,500000000",
+		)
+		.unwrap();
+		assert_eq!(count_overlaps_sweep(&endpoints, 2), 1_000_000_001 + 2);
+		assert_eq!(count_overlaps_sweep(&endpoints, 3), 1);
+
+		// Oblique lines this long overflow an `i64` when parametrized. The first two overlap
+		// for 2000000001 points, the third crosses them at (3000000000, 1500000000), and the
+		// fourth only has its endpoints as lattice points, one of which is on the first
+		let endpoints = get_lines::<i64>(
+			"0,0 -> 6000000000,3000000000
+			2000000000,1000000000 -> 8000000000,4000000000
+			0,3000000000 -> 6000000000,0
+			0,0 -> 3000000000,2999999999",
+		)
+		.unwrap();
+		assert_eq!(count_overlaps_sweep(&endpoints, 2), 2_000_000_001 + 1);
+		assert_eq!(count_overlaps_sweep(&endpoints, 3), 1);
+	}
+
+	#[test]
+	fn test_export() {
+		let endpoints = get_lines(include_str!("sample_input.txt")).unwrap();
+		let counter = get_point_counts(&endpoints, |_| true);
+
+		assert_eq!(count_at(&counter, Point(2, 2)), 2);
+		assert_eq!(count_at(&counter, Point(4, 4)), 3);
+		assert_eq!(count_at(&counter, Point(0, 1)), 0);
+		assert_eq!(points_with_count_at_least(&counter, 2).count(), 12);
+		assert_eq!(
+			points_with_count_at_least(&counter, 3).collect::<Vec<_>>(),
+			vec![(Point(4, 4), 3), (Point(6, 4), 3)]
+		);
+
+		let mut csv = Vec::new();
+		write_csv(&counter, 3, &mut csv).unwrap();
+		assert_eq!(String::from_utf8(csv).unwrap(), "x,y,count\n4,4,3\n6,4,3\n");
+
+		let mut pgm = Vec::new();
+		write_pgm(&counter, 2, &mut pgm).unwrap();
+		let header = b"P5\n10 10\n255\n";
+		assert_eq!(&pgm[..header.len()], header);
+		let pixels = &pgm[header.len()..];
+		assert_eq!(pixels.len(), 100);
+		assert_eq!(pixels[4 * 10 + 4], 255);
+		assert_eq!(pixels[2 * 10 + 2], 170);
+		assert_eq!(pixels.iter().filter(|&&p| p > 0).count(), 12);
+
+		let mut ppm = Vec::new();
+		write_ppm(&counter, 1, &mut ppm).unwrap();
+		let header = b"P6\n10 10\n255\n";
+		assert_eq!(&ppm[..header.len()], header);
+		assert_eq!(ppm.len(), header.len() + 300);
+		let pixel = |row: usize, col: usize| {
+			let i = header.len() + 3 * (row * 10 + col);
+			&ppm[i..i + 3]
+		};
+		assert_eq!(pixel(4, 6), [255, 255, 255]);
+		assert_eq!(pixel(0, 0), [255, 0, 0]);
+		assert_eq!(pixel(1, 0), [0, 0, 0]);
+	}
 }
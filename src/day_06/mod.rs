@@ -1,10 +1,11 @@
 // tag::setup[]
 use crate::Answer;
+use num::{BigUint, Zero};
 
-const N_TIMERS: usize = 9;
-type Timers = [usize; N_TIMERS];
+pub const N_TIMERS: usize = 9;
+pub type Timers = [usize; N_TIMERS];
 
-fn read_input(input: &str) -> Option<Timers> {
+pub fn read_input(input: &str) -> Option<Timers> {
 	let mut timers = [0; N_TIMERS];
 
 	let nums = input
@@ -60,6 +61,183 @@ fn pt2(timers: &Timers) -> usize {
 }
 // end::pt2[]
 
+// tag::matrix[]
+type Matrix<T> = [[T; N_TIMERS]; N_TIMERS];
+
+fn mat_from_fn<T>(f: impl Fn(usize, usize) -> T) -> Matrix<T> {
+	std::array::from_fn(|i| std::array::from_fn(|j| f(i, j)))
+}
+
+/// `m` such that `m · timers` is `timers` after one tick
+fn transition_matrix<T: Zero + num::One>() -> Matrix<T> {
+	mat_from_fn(|i, j| match (i, j) {
+		(6 | 8, 0) => T::one(),
+		(i, j) if j == i + 1 => T::one(),
+		_ => T::zero(),
+	})
+}
+
+/// Matrix product over a ring in which `mul_add(acc, x, y)` computes `acc + x * y`
+fn mat_mul<T: Clone + Zero>(
+	a: &Matrix<T>,
+	b: &Matrix<T>,
+	mul_add: &impl Fn(T, &T, &T) -> T,
+) -> Matrix<T> {
+	mat_from_fn(|i, j| (0..N_TIMERS).fold(T::zero(), |acc, k| mul_add(acc, &a[i][k], &b[k][j])))
+}
+
+/// The number of fish after `n_days`, via `m^n_days · timers` with `m^n_days` found by
+/// repeated squaring, i.e., with `O(log(n_days))` matrix products
+fn count_after_via_matrix<T: Clone + Zero + num::One>(
+	timers: &Timers,
+	mut n_days: u64,
+	from_usize: impl Fn(usize) -> T,
+	mul_add: impl Fn(T, &T, &T) -> T,
+) -> T {
+	let mut result = mat_from_fn(|i, j| if i == j { T::one() } else { T::zero() });
+
+	let mut power = transition_matrix();
+	while n_days > 0 {
+		if n_days & 1 == 1 {
+			result = mat_mul(&result, &power, &mul_add);
+		}
+		power = mat_mul(&power, &power, &mul_add);
+		n_days >>= 1;
+	}
+
+	let timers = timers.map(from_usize);
+	result.iter().fold(T::zero(), |acc, row| {
+		row.iter()
+			.zip(&timers)
+			.fold(acc, |acc, (m, t)| mul_add(acc, m, t))
+	})
+}
+
+/// The exact number of fish after `n_days`
+pub fn count_after_exact(timers: &Timers, n_days: u64) -> BigUint {
+	count_after_via_matrix(timers, n_days, BigUint::from, |acc, x, y| acc + x * y)
+}
+
+/// The number of fish after `n_days`, modulo `modulus`
+pub fn count_after_mod(timers: &Timers, n_days: u64, modulus: u64) -> u64 {
+	assert!(modulus > 0);
+	let m = u128::from(modulus);
+	count_after_via_matrix(
+		timers,
+		n_days,
+		|n| u64::try_from(n as u128 % m).unwrap(),
+		|acc, &x, &y| u64::try_from((u128::from(acc) + u128::from(x) * u128::from(y)) % m).unwrap(),
+	) % modulus
+}
+// end::matrix[]
+
+// tag::model[]
+/// A population in which each individual gives birth every `cycle_len` days (at which
+/// point its timer goes from 0 back to `cycle_len - 1`) to `offspring_per_birth` newborns,
+/// whose first cycle is `newborn_delay` days longer than the rest. With
+/// `lifespan_cycles: Some(k)`, individuals die right after giving birth for the `k`th time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PopulationModel {
+	pub cycle_len: usize,
+	pub newborn_delay: usize,
+	pub offspring_per_birth: usize,
+	pub lifespan_cycles: Option<usize>,
+}
+
+impl PopulationModel {
+	pub const LANTERNFISH: Self = Self {
+		cycle_len: 7,
+		newborn_delay: 2,
+		offspring_per_birth: 1,
+		lifespan_cycles: None,
+	};
+
+	fn n_timers(&self) -> usize {
+		self.cycle_len + self.newborn_delay
+	}
+
+	/// The number of individuals after `n_days`, given the number with each timer value
+	/// initially (all of them in their first cycle)
+	pub fn count_after(&self, initial_timers: &[usize], n_days: usize) -> usize {
+		if *self == Self::LANTERNFISH && initial_timers.len() <= N_TIMERS {
+			let mut timers = [0; N_TIMERS];
+			timers[..initial_timers.len()].copy_from_slice(initial_timers);
+			return tick(n_days, &timers).iter().sum();
+		}
+
+		let mut population = Population::new(*self, initial_timers);
+		for _ in 0..n_days {
+			population.tick();
+		}
+		population.total()
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Population {
+	model: PopulationModel,
+	/// `counts[n_births][timer]`. Without mortality, we don't need to know how many times
+	/// an individual has given birth, and so there's only `counts[0]`
+	counts: Vec<Vec<usize>>,
+}
+
+impl Population {
+	pub fn new(model: PopulationModel, initial_timers: &[usize]) -> Self {
+		assert!(model.cycle_len > 0);
+		assert_ne!(model.lifespan_cycles, Some(0));
+		assert!(initial_timers.len() <= model.n_timers());
+
+		let n_ages = model.lifespan_cycles.unwrap_or(1);
+		let mut counts = vec![vec![0; model.n_timers()]; n_ages];
+		counts[0][..initial_timers.len()].copy_from_slice(initial_timers);
+		Self { model, counts }
+	}
+
+	pub fn tick(&mut self) {
+		let PopulationModel {
+			cycle_len,
+			offspring_per_birth,
+			lifespan_cycles,
+			..
+		} = self.model;
+		let n_ages = self.counts.len();
+
+		let mut n_births = 0;
+		let mut parents_by_age = vec![0; n_ages];
+		for (age, timers) in self.counts.iter_mut().enumerate() {
+			let parents = timers[0];
+			timers.rotate_left(1);
+			*timers.last_mut().unwrap() = 0;
+
+			n_births += parents;
+			if lifespan_cycles.is_some() {
+				if age + 1 < n_ages {
+					parents_by_age[age + 1] += parents;
+				}
+			} else {
+				parents_by_age[age] += parents;
+			}
+		}
+
+		for (timers, parents) in self.counts.iter_mut().zip(parents_by_age) {
+			timers[cycle_len - 1] += parents;
+		}
+		*self.counts[0].last_mut().unwrap() += n_births * offspring_per_birth;
+	}
+
+	pub fn total(&self) -> usize {
+		self.counts.iter().flatten().sum()
+	}
+
+	/// The number of individuals with each timer value
+	pub fn timers(&self) -> Vec<usize> {
+		(0..self.model.n_timers())
+			.map(|timer| self.counts.iter().map(|timers| timers[timer]).sum())
+			.collect()
+	}
+}
+// end::model[]
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -70,4 +248,105 @@ mod test {
 		test_input!(include_str!("sample_input.txt"), day: 6, ans: (5934, 26_984_457_539));
 		test_input!(include_str!("input.txt"), day: 6, ans: (372_984, 1_681_503_251_694));
 	}
+
+	#[test]
+	fn test_matrix() {
+		let timers = read_input(include_str!("input.txt")).unwrap();
+		for n_days in [0, 1, 7, 80, 256, 400] {
+			let expected = tick(n_days, &timers).iter().sum::<usize>();
+			let n_days = u64::try_from(n_days).unwrap();
+			assert_eq!(count_after_exact(&timers, n_days), BigUint::from(expected));
+			assert_eq!(
+				count_after_mod(&timers, n_days, 1_000_000_007),
+				u64::try_from(expected % 1_000_000_007).unwrap()
+			);
+		}
+
+		let prime = 998_244_353;
+		let exact = count_after_exact(&timers, 10_000);
+		assert_eq!(
+			BigUint::from(count_after_mod(&timers, 10_000, prime)),
+			exact % prime
+		);
+
+		// Astronomically many days are no trouble modulo a prime
+		assert!(count_after_mod(&timers, u64::MAX, prime) < prime);
+		assert_eq!(count_after_mod(&timers, 10_000, 1), 0);
+	}
+
+	#[test]
+	fn test_model() {
+		// Simulate each individual, as `(timer, n_births)`
+		fn brute_force(model: PopulationModel, initial_timers: &[usize], n_days: usize) -> usize {
+			let mut individuals = initial_timers
+				.iter()
+				.enumerate()
+				.flat_map(|(timer, &n)| std::iter::repeat((timer, 0)).take(n))
+				.collect::<Vec<_>>();
+			for _ in 0..n_days {
+				let mut next = Vec::new();
+				for (timer, n_births) in individuals {
+					if timer > 0 {
+						next.push((timer - 1, n_births));
+						continue;
+					}
+					let newborn = (model.cycle_len + model.newborn_delay - 1, 0);
+					next.extend(std::iter::repeat(newborn).take(model.offspring_per_birth));
+					if model.lifespan_cycles != Some(n_births + 1) {
+						next.push((model.cycle_len - 1, n_births + 1));
+					}
+				}
+				individuals = next;
+			}
+			individuals.len()
+		}
+
+		let timers = read_input(include_str!("sample_input.txt")).unwrap();
+
+		// The general model agrees with the fast path
+		let mut population = Population::new(PopulationModel::LANTERNFISH, &timers);
+		for n_days in 1..=80 {
+			population.tick();
+			assert_eq!(population.timers(), tick(n_days, &timers).to_vec());
+		}
+		assert_eq!(
+			PopulationModel::LANTERNFISH.count_after(&timers, 256),
+			26_984_457_539
+		);
+
+		for model in [
+			PopulationModel {
+				cycle_len: 3,
+				newborn_delay: 1,
+				offspring_per_birth: 2,
+				lifespan_cycles: None,
+			},
+			PopulationModel {
+				lifespan_cycles: Some(2),
+				..PopulationModel::LANTERNFISH
+			},
+			PopulationModel {
+				cycle_len: 4,
+				newborn_delay: 0,
+				offspring_per_birth: 3,
+				lifespan_cycles: Some(3),
+			},
+		] {
+			for n_days in 0..40 {
+				assert_eq!(
+					model.count_after(&[1, 0, 2, 1], n_days),
+					brute_force(model, &[1, 0, 2, 1], n_days)
+				);
+			}
+		}
+
+		// Each individual gives birth once and then dies, keeping the population constant
+		let mayfly = PopulationModel {
+			cycle_len: 1,
+			newborn_delay: 0,
+			offspring_per_birth: 1,
+			lifespan_cycles: Some(1),
+		};
+		assert_eq!(mayfly.count_after(&[5], 100), 5);
+	}
 }
@@ -1,7 +1,7 @@
 // tag::setup[]
 use crate::{utils::abs_diff, Answer};
 
-fn read_input(s: &str) -> Option<Vec<usize>> {
+pub fn read_input(s: &str) -> Option<Vec<usize>> {
 	s.trim()
 		.split(',')
 		.map(|n| n.parse().ok())
@@ -10,7 +10,7 @@ fn read_input(s: &str) -> Option<Vec<usize>> {
 
 fn ans_for_input(input: &str) -> Answer<usize, usize> {
 	let nums = read_input(input).unwrap();
-	(7, (pt1(&nums), pt2(&nums))).into()
+	(7, (pt1(&nums).unwrap(), pt2(&nums).unwrap())).into()
 }
 
 pub fn ans() -> Answer<usize, usize> {
@@ -18,40 +18,249 @@ pub fn ans() -> Answer<usize, usize> {
 }
 // end::setup[]
 // tag::pt1[]
-fn pt1<V: AsRef<[usize]>>(nums: V) -> usize {
-	let mut nums = nums.as_ref().to_vec();
-	nums.sort_unstable();
-	let datum_below = nums[nums.len() / 2];
-	let datum_above = nums[1 + (nums.len() - 1) / 2];
-	let median = (datum_below + datum_above) / 2;
-	nums.iter().map(|&n| abs_diff(n, median)).sum()
+fn pt1(nums: &[usize]) -> Option<usize> {
+	optimize(nums, &Linear).map(|(_, fuel)| fuel)
 }
 // end::pt1[]
 // tag::pt2[]
-fn pt2<V: AsRef<[usize]>>(nums: V) -> usize {
-	fn cost(mean: usize, nums: &[usize]) -> usize {
-		nums.iter()
-			.map(|&n| {
-				let diff = abs_diff(n, mean);
-				diff * (diff + 1) / 2
-			})
-			.sum()
+fn pt2(nums: &[usize]) -> Option<usize> {
+	optimize(nums, &Triangular).map(|(_, fuel)| fuel)
+}
+// end::pt2[]
+
+// tag::costs[]
+/// The fuel it takes one crab to move `dist`. Costs are assumed to be non-decreasing in
+/// `dist`, so the best position is never outside the range of the crabs' positions
+pub trait FuelCost {
+	fn cost(&self, dist: usize) -> usize;
+
+	/// Whether `cost(d + 1) - cost(d)` is non-decreasing in `d`. If so, the total cost is
+	/// convex in the meeting point, which lets us search for it rather than try them all
+	fn is_convex(&self) -> bool {
+		false
+	}
+
+	/// A closed-form location of the optimum, if this cost has one
+	fn shortcut(&self) -> Option<Shortcut> {
+		None
 	}
+}
 
-	let nums = nums.as_ref();
-	let sum = nums.iter().sum::<usize>();
-	let len = nums.len();
+/// Where the optimum of a cost is known to lie
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shortcut {
+	/// At the (weighted) median
+	Median,
+	/// At the (weighted) mean, rounded one way or the other
+	Mean,
+}
 
-	let mean_rounded_down = sum / len;
+/// Each step costs 1
+pub struct Linear;
+/// The `k`th step costs `k`
+pub struct Triangular;
+/// Moving `d` costs `d^2`
+pub struct Quadratic;
+/// Any nonzero move costs the same
+pub struct ConstantPerMove(pub usize);
+/// A user-supplied cost that the user promises is convex
+pub struct Convex<F>(pub F);
+
+impl FuelCost for Linear {
+	fn cost(&self, dist: usize) -> usize {
+		dist
+	}
 
-	if sum % len == 0 {
-		cost(mean_rounded_down, nums)
-	} else {
-		let mean_rounded_up = (sum - 1) / len + 1;
-		cost(mean_rounded_down, nums).min(cost(mean_rounded_up, nums))
+	fn is_convex(&self) -> bool {
+		true
+	}
+
+	fn shortcut(&self) -> Option<Shortcut> {
+		Some(Shortcut::Median)
+	}
+}
+
+impl FuelCost for Triangular {
+	fn cost(&self, dist: usize) -> usize {
+		dist * (dist + 1) / 2
+	}
+
+	fn is_convex(&self) -> bool {
+		true
+	}
+
+	fn shortcut(&self) -> Option<Shortcut> {
+		Some(Shortcut::Mean)
+	}
+}
+
+impl FuelCost for Quadratic {
+	fn cost(&self, dist: usize) -> usize {
+		dist * dist
+	}
+
+	fn is_convex(&self) -> bool {
+		true
+	}
+
+	fn shortcut(&self) -> Option<Shortcut> {
+		Some(Shortcut::Mean)
+	}
+}
+
+impl FuelCost for ConstantPerMove {
+	fn cost(&self, dist: usize) -> usize {
+		if dist == 0 {
+			0
+		} else {
+			self.0
+		}
+	}
+}
+
+impl<F: Fn(usize) -> usize> FuelCost for Convex<F> {
+	fn cost(&self, dist: usize) -> usize {
+		self.0(dist)
+	}
+
+	fn is_convex(&self) -> bool {
+		true
+	}
+}
+
+/// Any other function of the distance; not assumed to be convex
+impl<F: Fn(usize) -> usize> FuelCost for F {
+	fn cost(&self, dist: usize) -> usize {
+		self(dist)
 	}
 }
-// end::pt2[]
+
+pub fn total_cost(nums: &[usize], target: usize, cost: &impl FuelCost) -> usize {
+	nums.iter().map(|&n| cost.cost(abs_diff(n, target))).sum()
+}
+
+/// Returns a meeting point minimizing the total cost, and that cost
+pub fn optimize(nums: &[usize], cost: &impl FuelCost) -> Option<(usize, usize)> {
+	let weighted = nums.iter().map(|&n| (n, 1)).collect::<Vec<_>>();
+	optimize_weighted(&weighted, cost)
+}
+// end::costs[]
+// tag::weighted[]
+/// A crab at `pos` (in `D` dimensions) that burns `weight` times as much fuel as usual
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crab<const D: usize> {
+	pub pos: [usize; D],
+	pub weight: usize,
+}
+
+/// Parses whitespace-separated crabs, each of the form `x,y,...` or `x,y,...*weight`
+pub fn read_crabs<const D: usize>(s: &str) -> Option<Vec<Crab<D>>> {
+	s.split_whitespace()
+		.map(|token| {
+			let (coords, weight) = match token.split_once('*') {
+				Some((coords, weight)) => (coords, weight.parse().ok()?),
+				None => (token, 1),
+			};
+			let coords = coords
+				.split(',')
+				.map(|c| c.parse().ok())
+				.collect::<Option<Vec<usize>>>()?;
+			let pos = coords.try_into().ok()?;
+			Some(Crab { pos, weight })
+		})
+		.collect()
+}
+
+/// Sum of `weight * cost(|pos - target|)` over `(pos, weight)` pairs
+pub fn total_weighted_cost(crabs: &[(usize, usize)], target: usize, cost: &impl FuelCost) -> usize {
+	crabs
+		.iter()
+		.map(|&(pos, weight)| weight * cost.cost(abs_diff(pos, target)))
+		.sum()
+}
+
+fn weighted_median(crabs: &[(usize, usize)], total_weight: usize) -> usize {
+	let mut crabs = crabs.to_vec();
+	crabs.sort_unstable();
+	let mut seen = 0;
+	for &(pos, weight) in &crabs {
+		seen += weight;
+		if 2 * seen >= total_weight {
+			return pos;
+		}
+	}
+	unreachable!("total weight is positive")
+}
+
+/// Returns a meeting point minimizing the total weighted cost, and that cost. Costs with a
+/// [`Shortcut`] go straight to the median or mean; other convex costs use an integer
+/// ternary search; anything else tries every position
+pub fn optimize_weighted(crabs: &[(usize, usize)], cost: &impl FuelCost) -> Option<(usize, usize)> {
+	let mut lo = crabs.iter().map(|&(pos, _)| pos).min()?;
+	let mut hi = crabs.iter().map(|&(pos, _)| pos).max()?;
+	let total = |target| total_weighted_cost(crabs, target, cost);
+
+	let total_weight = crabs.iter().map(|&(_, weight)| weight).sum::<usize>();
+	if total_weight > 0 {
+		match cost.shortcut() {
+			Some(Shortcut::Median) => {
+				let median = weighted_median(crabs, total_weight);
+				return Some((median, total(median)));
+			}
+			Some(Shortcut::Mean) => {
+				// The optimum is within 1/2 of the mean, so one of the two integers around
+				// it will do
+				let moment = crabs
+					.iter()
+					.map(|&(pos, weight)| pos * weight)
+					.sum::<usize>();
+				(lo, hi) = (
+					moment / total_weight,
+					(moment + total_weight - 1) / total_weight,
+				);
+			}
+			None => {}
+		}
+	}
+
+	if cost.is_convex() {
+		// Invariant: some minimizer lies in lo..=hi
+		while hi - lo > 2 {
+			let m1 = lo + (hi - lo) / 3;
+			let m2 = hi - (hi - lo) / 3;
+			match total(m1).cmp(&total(m2)) {
+				std::cmp::Ordering::Less => hi = m2 - 1,
+				std::cmp::Ordering::Greater => lo = m1 + 1,
+				std::cmp::Ordering::Equal => (lo, hi) = (m1, m2),
+			}
+		}
+	}
+
+	(lo..=hi)
+		.map(|target| (target, total(target)))
+		.min_by_key(|&(_, cost)| cost)
+}
+
+/// Returns a meeting point minimizing the total cost, where a crab's cost is the sum of
+/// the costs along each axis. The axes are independent, so each is optimized on its own
+pub fn optimize_crabs<const D: usize>(
+	crabs: &[Crab<D>],
+	cost: &impl FuelCost,
+) -> Option<([usize; D], usize)> {
+	let mut point = [0; D];
+	let mut total = 0;
+	for (axis, coord) in point.iter_mut().enumerate() {
+		let projected = crabs
+			.iter()
+			.map(|crab| (crab.pos[axis], crab.weight))
+			.collect::<Vec<_>>();
+		let (best, axis_cost) = optimize_weighted(&projected, cost)?;
+		*coord = best;
+		total += axis_cost;
+	}
+	Some((point, total))
+}
+// end::weighted[]
 
 #[cfg(test)]
 mod test {
@@ -62,4 +271,103 @@ mod test {
 	fn test() {
 		test_input!(include_str!("input.txt"), day: 7, ans: (328_187, 91_257_582));
 	}
+
+	#[test]
+	fn test_costs() {
+		fn exhaustive(nums: &[usize], cost: &impl FuelCost) -> usize {
+			let (lo, hi) = (*nums.iter().min().unwrap(), *nums.iter().max().unwrap());
+			(lo..=hi)
+				.map(|target| total_cost(nums, target, cost))
+				.min()
+				.unwrap()
+		}
+
+		let nums = read_input(include_str!("input.txt")).unwrap();
+		let (_, linear) = optimize(&nums, &Linear).unwrap();
+		let (_, triangular) = optimize(&nums, &Triangular).unwrap();
+		assert_eq!(
+			(linear, triangular),
+			(exhaustive(&nums, &Linear), exhaustive(&nums, &Triangular))
+		);
+
+		let sample = read_input("16,1,2,0,4,2,7,1,2,14").unwrap();
+		assert_eq!(optimize(&sample, &Linear), Some((2, 37)));
+		assert_eq!(optimize(&sample, &Triangular), Some((5, 168)));
+		assert_eq!(optimize(&sample, &ConstantPerMove(10)), Some((2, 70)));
+
+		let cube = Convex(|d: usize| d * d * d);
+		let step = |d: usize| d / 3;
+		for nums in [&nums, &sample] {
+			assert_eq!(
+				optimize(nums, &Quadratic).unwrap().1,
+				exhaustive(nums, &Quadratic)
+			);
+			assert_eq!(optimize(nums, &cube).unwrap().1, exhaustive(nums, &cube));
+			assert_eq!(optimize(nums, &step).unwrap().1, exhaustive(nums, &step));
+		}
+
+		assert_eq!(optimize(&[], &Linear), None);
+	}
+
+	#[test]
+	fn test_weighted() {
+		fn exhaustive<const D: usize>(crabs: &[Crab<D>], cost: &impl FuelCost) -> usize {
+			let max = crabs.iter().flat_map(|c| c.pos).max().unwrap();
+			let mut best = usize::MAX;
+			let mut point = [0; D];
+			loop {
+				let total = crabs
+					.iter()
+					.map(|c| {
+						let dists = c.pos.iter().zip(&point);
+						c.weight
+							* dists
+								.map(|(&p, &x)| cost.cost(abs_diff(p, x)))
+								.sum::<usize>()
+					})
+					.sum();
+				best = best.min(total);
+
+				let Some(axis) = point.iter().position(|&x| x < max) else {
+					return best;
+				};
+				point[..axis].fill(0);
+				point[axis] += 1;
+			}
+		}
+
+		let crabs = read_crabs::<1>("16 1 2 0 4 2 7 1 2 14").unwrap();
+		assert_eq!(optimize_crabs(&crabs, &Linear), Some(([2], 37)));
+		assert_eq!(optimize_crabs(&crabs, &Triangular), Some(([5], 168)));
+
+		// A heavy crab drags the median to itself
+		let crabs = read_crabs::<1>("0 1 10*5").unwrap();
+		assert_eq!(optimize_crabs(&crabs, &Linear), Some(([10], 19)));
+
+		let crabs = read_crabs::<2>("0,0 4,0*2 0,6 3,3*3 7,1").unwrap();
+		let crabs_3d = read_crabs::<3>("0,0,5 4,0,1*2 0,6,0 3,3,3*3 7,1,2 2,2,2*4").unwrap();
+		let step = |d: usize| d / 2;
+		let cube = Convex(|d: usize| d * d * d);
+		macro_rules! check {
+			($($cost:expr),*) => {$(
+				assert_eq!(optimize_crabs(&crabs, &$cost).unwrap().1, exhaustive(&crabs, &$cost));
+				assert_eq!(
+					optimize_crabs(&crabs_3d, &$cost).unwrap().1,
+					exhaustive(&crabs_3d, &$cost)
+				);
+			)*};
+		}
+		check!(
+			Linear,
+			Triangular,
+			Quadratic,
+			ConstantPerMove(3),
+			step,
+			cube
+		);
+
+		assert_eq!(optimize(&[0, 0], &Triangular), Some((0, 0)));
+		assert_eq!(read_crabs::<2>("1,2,3"), None);
+		assert_eq!(read_crabs::<2>("1,2*x"), None);
+	}
 }
@@ -1,116 +1,189 @@
 // tag::setup[]
 use crate::Answer;
-use std::collections::{btree_map::Entry as MapEntry, BTreeMap as Map, BTreeSet as Set};
-
-const N_SEGMENTS: usize = 7;
+use std::{
+	collections::{btree_map::Entry as MapEntry, BTreeMap as Map, BTreeSet as Set},
+	sync::OnceLock,
+};
 // end::setup[]
 
 // tag::digit[]
+/// A pattern of lit segments, with segment `i` (named `'a' + i`) stored in bit `i`
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-struct Digit {
-	segments: [bool; N_SEGMENTS],
-	n_on: usize,
+pub struct Digit {
+	segments: u32,
+	n_on: u32,
 }
 
 impl Digit {
-	fn new(segments: [bool; N_SEGMENTS]) -> Self {
+	fn new(segments: u32) -> Self {
 		Self {
 			segments,
-			n_on: segments.iter().filter(|&&b| b).count(),
+			n_on: segments.count_ones(),
 		}
 	}
 
-	fn from_str(s: &str) -> Self {
-		let mut segments = [false; N_SEGMENTS];
+	fn parse(s: &str, n_segments: usize) -> Option<Self> {
+		let mut segments = 0;
 		for c in s.bytes() {
-			let i = c - b'a';
-			segments[usize::from(i)] = true;
+			let i = usize::from(c.checked_sub(b'a')?);
+			if i >= n_segments {
+				return None;
+			}
+			segments |= 1 << i;
 		}
-		Self::new(segments)
-	}
-
-	fn _bin_op(self, rhs: Self, f: impl Fn(bool, bool) -> bool) -> Self {
-		Self::new(self.segments.zip(rhs.segments).map(|(x, y)| f(x, y)))
+		Some(Self::new(segments))
 	}
 }
 
 impl std::ops::BitOr for Digit {
 	type Output = Self;
 	fn bitor(self, rhs: Self) -> Self::Output {
-		self._bin_op(rhs, |x, y| x | y)
+		Self::new(self.segments | rhs.segments)
 	}
 }
 
 impl std::ops::BitAnd for Digit {
 	type Output = Self;
 	fn bitand(self, rhs: Self) -> Self::Output {
-		self._bin_op(rhs, |x, y| x & y)
+		Self::new(self.segments & rhs.segments)
 	}
 }
 
-impl std::ops::Not for Digit {
-	type Output = Self;
-	fn not(self) -> Self::Output {
-		Self::new(self.segments.map(|b| !b))
+impl From<usize> for Digit {
+	fn from(n: usize) -> Self {
+		let alphabet = Alphabet::seven_segment();
+		assert!(n < alphabet.len(), "Cannot make digit for n={n}");
+		alphabet.glyph(n)
 	}
 }
 
-impl From<usize> for Digit {
-	fn from(n: usize) -> Self {
-		let segments = match n {
-			/*
-			-----[A, B, C, D, E, F, G]
-			*/
-			0 => [1, 1, 1, 0, 1, 1, 1],
-			1 => [0, 0, 1, 0, 0, 1, 0],
-			2 => [1, 0, 1, 1, 1, 0, 1],
-			3 => [1, 0, 1, 1, 0, 1, 1],
-			4 => [0, 1, 1, 1, 0, 1, 0],
-			5 => [1, 1, 0, 1, 0, 1, 1],
-			6 => [1, 1, 0, 1, 1, 1, 1],
-			7 => [1, 0, 1, 0, 0, 1, 0],
-			8 => [1, 1, 1, 1, 1, 1, 1],
-			9 => [1, 1, 1, 1, 0, 1, 1],
-			_ => panic!("Cannot make digit for n={}", n),
-		};
-		let segments = segments.map(|i| i != 0);
-		Self::new(segments)
-	}
-}
-
-impl From<Digit> for usize {
-	fn from(digit: Digit) -> Self {
-		let segments = digit.segments.map(u8::from);
-		match segments {
-			/*
-			[A, B, C, D, E, F, G]
-			*/
-			[1, 1, 1, 0, 1, 1, 1] => 0,
-			[0, 0, 1, 0, 0, 1, 0] => 1,
-			[1, 0, 1, 1, 1, 0, 1] => 2,
-			[1, 0, 1, 1, 0, 1, 1] => 3,
-			[0, 1, 1, 1, 0, 1, 0] => 4,
-			[1, 1, 0, 1, 0, 1, 1] => 5,
-			[1, 1, 0, 1, 1, 1, 1] => 6,
-			[1, 0, 1, 0, 0, 1, 0] => 7,
-			[1, 1, 1, 1, 1, 1, 1] => 8,
-			[1, 1, 1, 1, 0, 1, 1] => 9,
-			_ => panic!("Digit {:?} is not valid", digit),
-		}
+/// A pattern that isn't any of the digits 0–9
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidDigit(pub Digit);
+
+impl std::fmt::Display for InvalidDigit {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Digit {:?} is not valid", self.0)
+	}
+}
+
+impl TryFrom<Digit> for usize {
+	type Error = InvalidDigit;
+	fn try_from(digit: Digit) -> Result<Self, Self::Error> {
+		Alphabet::seven_segment()
+			.index_of(digit)
+			.ok_or(InvalidDigit(digit))
 	}
 }
 // end::digit[]
 
+// tag::alphabet[]
+/// The glyphs a display can show, each a labeled pattern of its `n_segments` segments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alphabet {
+	n_segments: usize,
+	glyphs: Vec<(String, Digit)>,
+}
+
+macro_rules! builtin_alphabets {
+	($($name:ident: $file:literal),* $(,)?) => {
+		$(
+			pub fn $name() -> &'static Self {
+				static ALPHABET: OnceLock<Alphabet> = OnceLock::new();
+				ALPHABET.get_or_init(|| Self::parse(include_str!($file)).unwrap())
+			}
+		)*
+	};
+}
+
+impl Alphabet {
+	builtin_alphabets!(
+		seven_segment: "seven_segment.txt",
+		fourteen_segment: "fourteen_segment.txt",
+		sixteen_segment: "sixteen_segment.txt",
+	);
+
+	/// Parses a glyph set: a line `segments N` giving the number of segments (which are
+	/// named `a`, `b`, ...), then one line per glyph with its label and its lit segments,
+	/// e.g., `7 acf`. Blank lines and lines starting with `#` are skipped. Labels and
+	/// patterns must be unique
+	pub fn parse(s: &str) -> Option<Self> {
+		let mut lines = s
+			.lines()
+			.map(str::trim)
+			.filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+		let n_segments = lines
+			.next()?
+			.strip_prefix("segments")?
+			.trim()
+			.parse()
+			.ok()?;
+		if !(1..=32).contains(&n_segments) {
+			return None;
+		}
+
+		let mut glyphs = Vec::new();
+		let mut labels = Set::new();
+		let mut patterns = Set::new();
+		for line in lines {
+			let mut words = line.split_ascii_whitespace();
+			let label = words.next()?;
+			let digit = Digit::parse(words.next()?, n_segments)?;
+			if words.next().is_some() || !labels.insert(label) || !patterns.insert(digit) {
+				return None;
+			}
+			glyphs.push((label.to_owned(), digit));
+		}
+
+		Some(Self { n_segments, glyphs })
+	}
+
+	pub fn n_segments(&self) -> usize {
+		self.n_segments
+	}
+
+	pub fn len(&self) -> usize {
+		self.glyphs.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.glyphs.is_empty()
+	}
+
+	pub fn label(&self, idx: usize) -> &str {
+		&self.glyphs[idx].0
+	}
+
+	pub fn glyph(&self, idx: usize) -> Digit {
+		self.glyphs[idx].1
+	}
+
+	pub fn index_of(&self, digit: Digit) -> Option<usize> {
+		self.glyphs.iter().position(|&(_, d)| d == digit)
+	}
+
+	pub fn parse_digit(&self, s: &str) -> Option<Digit> {
+		Digit::parse(s, self.n_segments)
+	}
+
+	fn complement(&self, digit: Digit) -> Digit {
+		let all_on = u32::MAX >> (32 - self.n_segments);
+		Digit::new(!digit.segments & all_on)
+	}
+}
+// end::alphabet[]
+
 // tag::setup[]
 fn get_mapping_from_garbled_digits<D: std::borrow::Borrow<Digit>>(
+	alphabet: &Alphabet,
 	garbled_digits: impl Iterator<Item = D>,
 ) -> Result<Map<Digit, Digit>, Map<Digit, Set<Digit>>> {
 	let mut mappings = Map::new();
 
 	{
 		let mut grouped_by_n_on = Map::new();
-		for n in 0..=9 {
-			let digit = Digit::from(n);
+		for &(_, digit) in &alphabet.glyphs {
 			grouped_by_n_on
 				.entry(digit.n_on)
 				.or_insert_with(Set::new)
@@ -119,13 +192,13 @@ fn get_mapping_from_garbled_digits<D: std::borrow::Borrow<Digit>>(
 
 		for gd in garbled_digits {
 			let gd = *gd.borrow();
-			let digits_w_same_n_segments = &grouped_by_n_on[&gd.n_on];
-			mappings.insert(gd, digits_w_same_n_segments.clone());
+			let digits_w_same_n_segments = grouped_by_n_on.get(&gd.n_on).cloned();
+			mappings.insert(gd, digits_w_same_n_segments.unwrap_or_default());
 		}
 	}
 
 	let identity: &dyn Fn(Digit) -> _ = &(|x| x);
-	let bitwise_not: &dyn Fn(Digit) -> _ = &(|x| !x);
+	let bitwise_not: &dyn Fn(Digit) -> _ = &(|x| alphabet.complement(x));
 
 	loop {
 		let mut new_mappings = Map::new();
@@ -187,7 +260,7 @@ fn get_mapping_from_garbled_digits<D: std::borrow::Borrow<Digit>>(
 			new_mappings.remove(k);
 		}
 
-		if mappings.len() == N_SEGMENTS && mappings.values().all(|m| m.len() == 1) {
+		if mappings.len() == alphabet.n_segments && mappings.values().all(|m| m.len() == 1) {
 			return Ok(mappings
 				.into_iter()
 				.map(|(k, v)| (k, v.iter().next().copied().unwrap()))
@@ -200,58 +273,76 @@ fn get_mapping_from_garbled_digits<D: std::borrow::Borrow<Digit>>(
 	}
 }
 
-fn apply_mapping_to_garbled_digit(mapping: &Map<Digit, Digit>, garbled_digit: Digit) -> usize {
-	let mut result = Digit::new([false; 7]);
+fn apply_mapping_to_garbled_digit(mapping: &Map<Digit, Digit>, garbled_digit: Digit) -> Digit {
+	let mut result = Digit::new(0);
 	for (&k, &v) in mapping {
 		if (garbled_digit & k).n_on > 0 {
 			result = result | v;
 		}
 	}
-	result.into()
+	result
 }
 
-fn read_input(input: &str) -> Vec<(Vec<Digit>, Vec<Digit>)> {
-	fn whitespace_sepd_strs_to_digits(strs: &str) -> Vec<Digit> {
-		strs.trim()
-			.split_ascii_whitespace()
-			.map(Digit::from_str)
-			.collect()
-	}
+fn read_line(alphabet: &Alphabet, line: &str) -> Option<(Vec<Digit>, Vec<Digit>)> {
+	let whitespace_sepd_strs_to_digits = |strs: &str| {
+		strs.split_ascii_whitespace()
+			.map(|s| alphabet.parse_digit(s))
+			.collect::<Option<Vec<_>>>()
+	};
+	let mut in_out = line.split('|');
+	let in_digits = whitespace_sepd_strs_to_digits(in_out.next()?)?;
+	let out_digits = whitespace_sepd_strs_to_digits(in_out.next()?)?;
+	Some((in_digits, out_digits))
+}
+
+fn read_input(input: &str) -> Option<Vec<(Vec<Digit>, Vec<Digit>)>> {
 	input
 		.lines()
-		.filter_map(|line| {
-			let line = line.trim();
-			if line.is_empty() {
-				return None;
-			}
-			let mut in_out = line.split('|');
-			let in_digits = whitespace_sepd_strs_to_digits(in_out.next()?);
-			let out_digits = whitespace_sepd_strs_to_digits(in_out.next()?);
-			Some((in_digits, out_digits))
-		})
+		.map(str::trim)
+		.filter(|line| !line.is_empty())
+		.map(|line| read_line(Alphabet::seven_segment(), line))
 		.collect()
 }
 
+/// Returns the index in `alphabet` of each output glyph, or if the wiring can't be
+/// pinned down well enough to read them, what we do know about it
 fn translate_line_to_digits<D: std::borrow::Borrow<Digit>>(
+	alphabet: &Alphabet,
 	idod: (impl Iterator<Item = D>, impl Iterator<Item = D>),
-) -> Option<Vec<usize>> {
+) -> Result<Vec<usize>, Ambiguity> {
 	let (in_digits, out_digits) = idod;
+	let in_digits = in_digits.map(|d| *d.borrow()).collect::<Vec<_>>();
+	let out_digits = out_digits.map(|d| *d.borrow()).collect::<Vec<_>>();
 
-	let mapping = get_mapping_from_garbled_digits(in_digits).ok()?;
-	Some(
-		out_digits
-			.map(|d| apply_mapping_to_garbled_digit(&mapping, *d.borrow()))
-			.collect(),
-	)
+	let candidates = match get_mapping_from_garbled_digits(alphabet, in_digits.iter()) {
+		Ok(mapping) => {
+			if let Some(translated) = out_digits
+				.iter()
+				.map(|&d| alphabet.index_of(apply_mapping_to_garbled_digit(&mapping, d)))
+				.collect()
+			{
+				return Ok(translated);
+			}
+			mapping
+				.into_iter()
+				.map(|(k, v)| (k, Set::from([v])))
+				.collect()
+		}
+		Err(candidates) => candidates,
+	};
+
+	diagnose(alphabet, candidates, &in_digits, &out_digits)
 }
 
 fn ans_for_input(input: &str) -> Answer<usize, usize> {
-	let in_out_lines = read_input(input);
+	let in_out_lines = read_input(input).unwrap();
 
 	let output_digits = in_out_lines
 		.iter()
-		.map(|(in_d, out_d)| translate_line_to_digits((in_d.iter(), out_d.iter())))
-		.collect::<Option<Vec<_>>>()
+		.map(|(in_d, out_d)| {
+			translate_line_to_digits(Alphabet::seven_segment(), (in_d.iter(), out_d.iter()))
+		})
+		.collect::<Result<Vec<_>, _>>()
 		.unwrap();
 	(8, (pt1(output_digits.iter()), pt2(output_digits.iter()))).into()
 }
@@ -261,6 +352,296 @@ pub fn ans() -> Answer<usize, usize> {
 }
 // end::setup[]
 
+// tag::diagnostics[]
+/// A wiring, as a map from each single garbled segment to the segment it should be
+/// connected to
+pub type Wiring = Map<Digit, Digit>;
+
+/// The most wirings an `Ambiguity` lists. With many segments and few observations, the
+/// consistent wirings can number in the billions
+pub const MAX_WIRINGS: usize = 1000;
+
+/// What we know about a line whose outputs we couldn't read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ambiguity {
+	/// Where constraint propagation stopped: garbled patterns and the true patterns each
+	/// might be
+	pub candidates: Map<Digit, Set<Digit>>,
+	/// Wirings under which all of the line's glyphs, observed and output, are valid, each
+	/// as the segment that each garbled segment is connected to (see
+	/// `wiring_from_targets`). At most `MAX_WIRINGS` are listed
+	pub wirings: Vec<Vec<u8>>,
+	/// Whether more wirings fit than are listed
+	pub truncated: bool,
+	/// The output positions without exactly one reading, with the indices of the glyphs
+	/// each could be. (If no wiring fits, every output is listed, with no readings.)
+	pub ambiguous_outputs: Map<usize, Set<usize>>,
+}
+
+/// The wiring that connects garbled segment `i` to segment `targets[i]`
+pub fn wiring_from_targets<T: Copy + Into<usize>>(targets: &[T]) -> Wiring {
+	targets
+		.iter()
+		.enumerate()
+		.map(|(i, &target)| (Digit::new(1 << i), Digit::new(1 << target.into())))
+		.collect()
+}
+
+/// Narrows down a partial wiring, given as pairs `(garbled, targets)` of sets of segments
+/// such that the garbled segments are connected to the targets in some order, by
+/// requiring that the garbled pattern `from` be connected to exactly the segments of `to`.
+/// Each pair splits into its segments inside the patterns and those outside them, and the
+/// wiring is impossible if the two sides of any part differ in size
+fn refine(pairs: &[(u32, u32)], from: Digit, to: Digit) -> Option<Vec<(u32, u32)>> {
+	let mut refined = Vec::with_capacity(pairs.len() + 1);
+	for &(garbled, targets) in pairs {
+		for part in [
+			(garbled & from.segments, targets & to.segments),
+			(garbled & !from.segments, targets & !to.segments),
+		] {
+			if part.0.count_ones() != part.1.count_ones() {
+				return None;
+			}
+			if part.0 != 0 {
+				refined.push(part);
+			}
+		}
+	}
+	Some(refined)
+}
+
+/// For each way of choosing a true pattern for every garbled pattern in `constraints` such
+/// that some wiring connects each to its choice, calls `f` with the choices and the
+/// resulting pairs (as in `refine`)
+fn for_each_consistent_choice(
+	constraints: &[(Digit, Vec<Digit>)],
+	pairs: &[(u32, u32)],
+	chosen: &mut Vec<Digit>,
+	f: &mut impl FnMut(&[Digit], &[(u32, u32)]),
+) {
+	let Some(((garbled, choices), rest)) = constraints.split_first() else {
+		f(chosen, pairs);
+		return;
+	};
+	for &choice in choices {
+		if let Some(refined) = refine(pairs, *garbled, choice) {
+			chosen.push(choice);
+			for_each_consistent_choice(rest, &refined, chosen, f);
+			chosen.pop();
+		}
+	}
+}
+
+/// Extends `targets` (the targets of the first few garbled segments) in every way allowed
+/// by `allowed`, the set of possible targets of each garbled segment, until `wirings`
+/// holds `limit` of them
+fn push_wirings(allowed: &[u32], targets: &mut Vec<u8>, wirings: &mut Vec<Vec<u8>>, limit: usize) {
+	let Some(&options) = allowed.get(targets.len()) else {
+		wirings.push(targets.clone());
+		return;
+	};
+	let used = targets.iter().fold(0_u32, |used, &t| used | 1 << t);
+	let mut free = options & !used;
+	while free != 0 && wirings.len() < limit {
+		targets.push(u8::try_from(free.trailing_zeros()).unwrap());
+		push_wirings(allowed, targets, wirings, limit);
+		targets.pop();
+		free &= free - 1;
+	}
+}
+
+/// Rather than try wirings one segment at a time, we choose a true pattern for each
+/// garbled pattern, one pattern at a time. Each choice splits the segments into groups
+/// that must be wired to each other, and we back out as soon as two such groups differ in
+/// size. Which wirings fit follows directly from the groups, so however many there are,
+/// the search is only as large as the number of choices
+fn diagnose(
+	alphabet: &Alphabet,
+	candidates: Map<Digit, Set<Digit>>,
+	in_digits: &[Digit],
+	out_digits: &[Digit],
+) -> Result<Vec<usize>, Ambiguity> {
+	let mut grouped_by_n_on = Map::new();
+	for &(_, digit) in &alphabet.glyphs {
+		grouped_by_n_on
+			.entry(digit.n_on)
+			.or_insert_with(Set::new)
+			.insert(digit);
+	}
+
+	let mut choices = Map::new();
+	for &garbled in in_digits.iter().chain(out_digits) {
+		choices.entry(garbled).or_insert_with(|| {
+			grouped_by_n_on
+				.get(&garbled.n_on)
+				.cloned()
+				.unwrap_or_default()
+		});
+	}
+	for (&garbled, known) in &candidates {
+		match choices.entry(garbled) {
+			MapEntry::Vacant(v) => {
+				v.insert(known.clone());
+			}
+			MapEntry::Occupied(mut o) => {
+				o.insert(o.get() & known);
+			}
+		}
+	}
+
+	// Making the most constrained choices first prunes the search soonest
+	let mut constraints = choices
+		.into_iter()
+		.map(|(garbled, choices)| (garbled, choices.into_iter().collect::<Vec<_>>()))
+		.collect::<Vec<_>>();
+	constraints.sort_by_key(|(_, choices)| choices.len());
+	let out_positions = out_digits
+		.iter()
+		.map(|&d| {
+			constraints
+				.iter()
+				.position(|&(garbled, _)| garbled == d)
+				.unwrap()
+		})
+		.collect::<Vec<_>>();
+
+	let all_on = alphabet.complement(Digit::new(0)).segments;
+	let mut readings = vec![Set::new(); out_digits.len()];
+	let mut wirings = Vec::new();
+	for_each_consistent_choice(
+		&constraints,
+		&[(all_on, all_on)],
+		&mut Vec::new(),
+		&mut |chosen, pairs| {
+			for (reading, &pos) in readings.iter_mut().zip(&out_positions) {
+				// Outputs are only ever matched with glyphs
+				reading.insert(alphabet.index_of(chosen[pos]).unwrap());
+			}
+
+			let mut allowed = vec![0; alphabet.n_segments];
+			for &(garbled, targets) in pairs {
+				for (i, options) in allowed.iter_mut().enumerate() {
+					if garbled & (1 << i) != 0 {
+						*options = targets;
+					}
+				}
+			}
+			push_wirings(&allowed, &mut Vec::new(), &mut wirings, MAX_WIRINGS + 1);
+		},
+	);
+	let truncated = wirings.len() > MAX_WIRINGS;
+	wirings.truncate(MAX_WIRINGS);
+
+	if readings.iter().all(|r| r.len() == 1) {
+		return Ok(readings
+			.into_iter()
+			.map(|r| r.into_iter().next().unwrap())
+			.collect());
+	}
+
+	Err(Ambiguity {
+		candidates,
+		wirings,
+		truncated,
+		ambiguous_outputs: readings
+			.into_iter()
+			.enumerate()
+			.filter(|(_, r)| r.len() != 1)
+			.collect(),
+	})
+}
+// end::diagnostics[]
+
+// tag::brute_force[]
+/// The segments lit by `garbled` once segment `i` is connected to segment `targets[i]`
+fn rewire(garbled: u32, targets: &[usize]) -> u32 {
+	targets
+		.iter()
+		.enumerate()
+		.filter(|&(i, _)| garbled & (1 << i) != 0)
+		.fold(0, |acc, (_, &target)| acc | 1 << target)
+}
+
+/// Calls `f` on every permutation of `0..n`, using Heap's algorithm
+fn for_each_permutation(n: usize, mut f: impl FnMut(&[usize])) {
+	let mut perm = (0..n).collect::<Vec<_>>();
+	let mut counters = vec![0; n];
+	f(&perm);
+
+	let mut i = 1;
+	while i < n {
+		if counters[i] < i {
+			let j = if i % 2 == 0 { 0 } else { counters[i] };
+			perm.swap(j, i);
+			f(&perm);
+			counters[i] += 1;
+			i = 1;
+		} else {
+			counters[i] = 0;
+			i += 1;
+		}
+	}
+}
+
+/// Every wiring under which all of `garbled_digits` are glyphs of `alphabet`, found by
+/// trying each of the `n_segments!` wirings in turn (5040 of them for seven segments)
+pub fn brute_force_wirings(alphabet: &Alphabet, garbled_digits: &[Digit]) -> Vec<Wiring> {
+	let mut valid = alphabet
+		.glyphs
+		.iter()
+		.map(|(_, d)| d.segments)
+		.collect::<Vec<_>>();
+	valid.sort_unstable();
+
+	let mut wirings = Vec::new();
+	for_each_permutation(alphabet.n_segments, |targets| {
+		if garbled_digits
+			.iter()
+			.all(|d| valid.binary_search(&rewire(d.segments, targets)).is_ok())
+		{
+			wirings.push(wiring_from_targets(targets));
+		}
+	});
+	wirings
+}
+
+/// Reads the outputs of a seven-segment line by brute force, if only one wiring fits
+pub fn translate_line_brute_force(in_digits: &[Digit], out_digits: &[Digit]) -> Option<Vec<usize>> {
+	let wirings = brute_force_wirings(Alphabet::seven_segment(), &[in_digits, out_digits].concat());
+	let [wiring] = wirings.as_slice() else {
+		return None;
+	};
+	out_digits
+		.iter()
+		.map(|&d| usize::try_from(apply_mapping_to_garbled_digit(wiring, d)).ok())
+		.collect()
+}
+// end::brute_force[]
+
+// tag::alphabet[]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+	/// The line isn't of the form `<observed glyphs> | <output glyphs>`, or uses segments
+	/// the display doesn't have
+	Malformed,
+	Ambiguous(Ambiguity),
+}
+
+/// Decodes a line of the form `<observed glyphs> | <output glyphs>`, all garbled the same
+/// way, into the labels of the output glyphs
+///
+/// # Errors
+///
+/// `Malformed` if the line can't be read with `alphabet`, and `Ambiguous` if the observed
+/// glyphs don't pin down the wiring enough to read the output glyphs
+pub fn decode_line(alphabet: &Alphabet, line: &str) -> Result<String, DecodeError> {
+	let (in_digits, out_digits) = read_line(alphabet, line).ok_or(DecodeError::Malformed)?;
+	let indices = translate_line_to_digits(alphabet, (in_digits.iter(), out_digits.iter()))
+		.map_err(DecodeError::Ambiguous)?;
+	Ok(indices.into_iter().map(|i| alphabet.label(i)).collect())
+}
+// end::alphabet[]
+
 // tag::pt1[]
 fn pt1<Nums: AsRef<[usize]>>(out_digits: impl Iterator<Item = Nums>) -> usize {
 	out_digits
@@ -298,4 +679,292 @@ mod test {
 	fn test() {
 		test_input!(include_str!("input.txt"), day: 8, ans: (237, 1_009_098));
 	}
+
+	/// Writes `observed | message` as `alphabet` would show it with segment `i` wired to
+	/// segment `wiring[i]`
+	fn garble(alphabet: &Alphabet, wiring: &[usize], observed: &[usize], message: &str) -> String {
+		let garble_glyph = |idx: usize| {
+			let segments = alphabet.glyph(idx).segments;
+			let mut garbled = (0..alphabet.n_segments())
+				.filter(|&i| segments & (1 << i) != 0)
+				.map(|i| char::from(b'a' + u8::try_from(wiring[i]).unwrap()))
+				.collect::<Vec<_>>();
+			garbled.sort_unstable();
+			garbled.into_iter().collect::<String>()
+		};
+		let observed = observed
+			.iter()
+			.map(|&i| garble_glyph(i))
+			.collect::<Vec<_>>();
+		let message = message
+			.chars()
+			.map(|c| {
+				garble_glyph(
+					(0..alphabet.len())
+						.find(|&i| alphabet.label(i) == c.to_string())
+						.unwrap(),
+				)
+			})
+			.collect::<Vec<_>>();
+		format!("{} | {}", observed.join(" "), message.join(" "))
+	}
+
+	#[test]
+	fn test_alphabets() {
+		let seven = Alphabet::seven_segment();
+		assert_eq!((seven.n_segments(), seven.len()), (7, 10));
+		assert_eq!(usize::try_from(Digit::from(4)), Ok(4));
+		assert_eq!(
+			decode_line(
+				seven,
+				include_str!("sample_input.txt").lines().next().unwrap()
+			),
+			Ok("5353".to_owned())
+		);
+
+		let mut rng = crate::utils::SplitMix64::new(8);
+		for (alphabet, message) in [
+			(Alphabet::fourteen_segment(), "HELLOWORLD2021"),
+			(Alphabet::sixteen_segment(), "SEVENSEGMENTSEARCH"),
+		] {
+			assert_eq!(alphabet.len(), 36);
+			let mut wiring = (0..alphabet.n_segments()).collect::<Vec<_>>();
+			let mut observed = (0..alphabet.len()).collect::<Vec<_>>();
+			rng.shuffle(&mut wiring);
+			rng.shuffle(&mut observed);
+			let line = garble(alphabet, &wiring, &observed, message);
+			assert_eq!(decode_line(alphabet, &line), Ok(message.to_owned()));
+		}
+
+		let custom = Alphabet::parse(
+			"# a four-segment display\nsegments 4\n\nx a\ny ab\nz abc\nw abcd\nv bd\n",
+		)
+		.unwrap();
+		assert_eq!(
+			(custom.n_segments(), custom.len(), custom.label(4)),
+			(4, 5, "v")
+		);
+		assert_eq!(
+			decode_line(&custom, "d cd bcd abcd ac | ac d"),
+			Ok("vx".to_owned())
+		);
+
+		assert_eq!(Alphabet::parse("segments 3\nx ab\ny abd"), None);
+		assert_eq!(Alphabet::parse("segments 3\nx ab\nx abc"), None);
+		assert_eq!(Alphabet::parse("segments 3\nx ab\ny ba"), None);
+		assert_eq!(Alphabet::parse("x ab"), None);
+	}
+
+	#[test]
+	fn test_diagnostics() {
+		let seven = Alphabet::seven_segment();
+		// The wiring in the first line of the sample input, and its outputs, 5353
+		let wiring = [3, 4, 0, 5, 6, 1, 2];
+		let line = |observed: &[usize]| garble(seven, &wiring, observed, "5353");
+		let line_digits = |line: &str| read_line(seven, line).unwrap().0.into_iter();
+		let mut true_targets = [0_u8; 7];
+		for (i, &target) in wiring.iter().enumerate() {
+			true_targets[target] = u8::try_from(i).unwrap();
+		}
+		let true_wiring = wiring_from_targets(&true_targets);
+
+		// 1 and 7 (plus the outputs) aren't enough to tell 2 from 5
+		let Err(DecodeError::Ambiguous(ambiguity)) = decode_line(seven, &line(&[1, 7])) else {
+			panic!("expected an ambiguity");
+		};
+		assert_eq!((ambiguity.wirings.len(), ambiguity.truncated), (4, false));
+		assert!(ambiguity.wirings.contains(&true_targets.to_vec()));
+		assert_eq!(
+			ambiguity.ambiguous_outputs,
+			Map::from([(0, Set::from([2, 5])), (2, Set::from([2, 5]))])
+		);
+		assert!(ambiguity
+			.candidates
+			.iter()
+			.all(|(&garbled, choices)| choices
+				.contains(&apply_mapping_to_garbled_digit(&true_wiring, garbled))));
+
+		// Propagation alone gets stuck here, but every wiring that fits reads the same
+		assert!(get_mapping_from_garbled_digits(seven, line_digits(&line(&[1, 4, 7]))).is_err());
+		assert_eq!(decode_line(seven, &line(&[1, 4, 7])), Ok("5353".to_owned()));
+
+		// With no observations at all, each output could be any digit with the same number
+		// of segments
+		let Err(DecodeError::Ambiguous(ambiguity)) = decode_line(seven, &line(&[])) else {
+			panic!("expected an ambiguity");
+		};
+		assert_eq!(ambiguity.ambiguous_outputs[&1], Set::from([2, 3, 5]));
+		assert_eq!((ambiguity.wirings.len(), ambiguity.truncated), (96, false));
+
+		// If `ab` is a 1 and `abc` a 7, then `ac` can't be a 1 too
+		let Err(DecodeError::Ambiguous(ambiguity)) = decode_line(seven, "ab abc | ac ab") else {
+			panic!("expected an ambiguity");
+		};
+		assert!(ambiguity.wirings.is_empty() && !ambiguity.truncated);
+		assert_eq!(
+			ambiguity.ambiguous_outputs,
+			Map::from([(0, Set::new()), (1, Set::new())])
+		);
+
+		assert_eq!(decode_line(seven, "ab | xy"), Err(DecodeError::Malformed));
+
+		// A single observed glyph leaves billions of sixteen-segment wirings, but only 8 has
+		// ten segments, two of them shared with a three-segment glyph
+		let sixteen = Alphabet::sixteen_segment();
+		assert_eq!(decode_line(sixteen, "cdm | abcdefghij"), Ok("8".to_owned()));
+
+		// Without it, the output could be either ten-segment glyph. We only list a few of
+		// the wirings, but all of them fit
+		let Err(DecodeError::Ambiguous(ambiguity)) = decode_line(sixteen, " | abcdefghij") else {
+			panic!("expected an ambiguity");
+		};
+		assert_eq!(
+			(ambiguity.wirings.len(), ambiguity.truncated),
+			(MAX_WIRINGS, true)
+		);
+		let ten_segment_glyphs = (0..sixteen.len())
+			.filter(|&i| sixteen.glyph(i).n_on == 10)
+			.collect::<Set<_>>();
+		assert_eq!(ambiguity.ambiguous_outputs[&0], ten_segment_glyphs);
+		for targets in &ambiguity.wirings {
+			let wiring = wiring_from_targets(targets);
+			let digit = sixteen.parse_digit("abcdefghij").unwrap();
+			assert!(sixteen
+				.index_of(apply_mapping_to_garbled_digit(&wiring, digit))
+				.is_some());
+		}
+
+		// A garbled fourteen-segment "2021", with two other glyphs observed. Nothing on the
+		// line tells the 2s from Ss
+		let fourteen = Alphabet::fourteen_segment();
+		let line = "ghim abdejkl | bdikln bdeghikm bdikln deh";
+		let Err(DecodeError::Ambiguous(ambiguity)) = decode_line(fourteen, line) else {
+			panic!("expected an ambiguity");
+		};
+		let two_or_s = Set::from(["2", "S"].map(|label| {
+			(0..fourteen.len())
+				.find(|&i| fourteen.label(i) == label)
+				.unwrap()
+		}));
+		assert_eq!(
+			ambiguity.ambiguous_outputs,
+			Map::from([(0, two_or_s.clone()), (2, two_or_s)])
+		);
+		assert_eq!((ambiguity.wirings.len(), ambiguity.truncated), (32, false));
+		let (in_digits, out_digits) = read_line(fourteen, line).unwrap();
+		for targets in &ambiguity.wirings {
+			let wiring = wiring_from_targets(targets);
+			assert!(in_digits.iter().chain(&out_digits).all(|&d| fourteen
+				.index_of(apply_mapping_to_garbled_digit(&wiring, d))
+				.is_some()));
+		}
+	}
+
+	fn random_line(
+		rng: &mut crate::utils::SplitMix64,
+		n_observed: usize,
+	) -> (Vec<Digit>, Vec<Digit>) {
+		let seven = Alphabet::seven_segment();
+		let mut wiring = (0..7).collect::<Vec<_>>();
+		let mut observed = (0..10).collect::<Vec<_>>();
+		rng.shuffle(&mut wiring);
+		rng.shuffle(&mut observed);
+		let message = (0..4)
+			.map(|_| char::from(b'0' + u8::try_from(rng.below(10)).unwrap()))
+			.collect::<String>();
+		read_line(
+			seven,
+			&garble(seven, &wiring, &observed[..n_observed], &message),
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn test_brute_force() {
+		let seven = Alphabet::seven_segment();
+		assert_eq!(
+			usize::try_from(Digit::new(0b11)),
+			Err(InvalidDigit(Digit::new(0b11)))
+		);
+
+		let (in_digits, out_digits) = read_line(
+			seven,
+			include_str!("sample_input.txt").lines().next().unwrap(),
+		)
+		.unwrap();
+		assert_eq!(
+			translate_line_brute_force(&in_digits, &out_digits),
+			Some(vec![5, 3, 5, 3])
+		);
+
+		let mut rng = crate::utils::SplitMix64::new(43);
+		for trial in 0..300 {
+			let (in_digits, out_digits) = random_line(&mut rng, trial % 11);
+			let all_digits = [&in_digits[..], &out_digits].concat();
+			let brute_force = brute_force_wirings(seven, &all_digits);
+			assert!(!brute_force.is_empty());
+
+			if let Ok(mapping) = get_mapping_from_garbled_digits(seven, in_digits.iter()) {
+				assert_eq!(brute_force, vec![mapping]);
+			}
+
+			match translate_line_to_digits(seven, (in_digits.iter(), out_digits.iter())) {
+				Ok(digits) => {
+					for wiring in &brute_force {
+						let read = out_digits
+							.iter()
+							.map(|&d| usize::try_from(apply_mapping_to_garbled_digit(wiring, d)))
+							.collect::<Result<Vec<_>, _>>();
+						assert_eq!(read.as_ref(), Ok(&digits));
+					}
+				}
+				Err(ambiguity) => {
+					let listed = ambiguity
+						.wirings
+						.iter()
+						.map(|targets| wiring_from_targets(targets))
+						.collect::<Set<_>>();
+					let brute_force = brute_force.into_iter().collect::<Set<_>>();
+					if ambiguity.truncated {
+						assert_eq!(listed.len(), MAX_WIRINGS);
+						assert!(listed.is_subset(&brute_force) && brute_force.len() > MAX_WIRINGS);
+					} else {
+						assert_eq!(listed, brute_force);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	#[ignore = "benchmark; run with `cargo test --release -- --ignored --nocapture`"]
+	fn bench_solvers() {
+		use std::time::Instant;
+
+		let seven = Alphabet::seven_segment();
+		let mut rng = crate::utils::SplitMix64::new(8);
+		let lines = (0..2_000)
+			.map(|_| random_line(&mut rng, 10))
+			.collect::<Vec<_>>();
+
+		let start = Instant::now();
+		let propagated = lines
+			.iter()
+			.map(|(in_d, out_d)| translate_line_to_digits(seven, (in_d.iter(), out_d.iter())).ok())
+			.collect::<Vec<_>>();
+		let propagation_time = start.elapsed();
+
+		let start = Instant::now();
+		let brute_forced = lines
+			.iter()
+			.map(|(in_d, out_d)| translate_line_brute_force(in_d, out_d))
+			.collect::<Vec<_>>();
+		let brute_force_time = start.elapsed();
+
+		assert_eq!(propagated, brute_forced);
+		println!(
+			"{} lines: constraint propagation {propagation_time:?}, brute force {brute_force_time:?}",
+			lines.len()
+		);
+	}
 }
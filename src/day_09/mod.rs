@@ -1,29 +1,72 @@
 // tag::setup[]
 use crate::Answer;
 use ndarray::prelude::*;
-use std::collections::BTreeSet as Set;
+use std::collections::{BTreeMap as Map, BTreeSet as Set, VecDeque};
+
+/// Which cells count as a cell's neighbors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Connectivity {
+	/// Whether the four diagonal neighbors count, in addition to the four orthogonal ones
+	pub diagonal: bool,
+	/// Whether the grid wraps around, so that the cells on opposite edges are neighbors
+	pub wrap: bool,
+}
 
 #[derive(Debug)]
-struct Heightmap {
+pub struct Heightmap {
 	arr: Array2<usize>,
+	connectivity: Connectivity,
 }
 
 fn get_neighbor_idxs(
 	[row, col]: [usize; 2],
 	n_rows: usize,
 	n_cols: usize,
-) -> [[Option<usize>; 2]; 4] {
-	[
-		[(row + 1 < n_rows).then_some(row + 1), Some(col)],
-		[row.checked_sub(1), Some(col)],
-		[Some(row), (col + 1 < n_cols).then_some(col + 1)],
-		[Some(row), col.checked_sub(1)],
-	]
+	connectivity: Connectivity,
+) -> Vec<[usize; 2]> {
+	// The orthogonal offsets come first
+	const OFFSETS: [[isize; 2]; 8] = [
+		[1, 0],
+		[-1, 0],
+		[0, 1],
+		[0, -1],
+		[1, 1],
+		[1, -1],
+		[-1, 1],
+		[-1, -1],
+	];
+
+	let step = |x: usize, dx: isize, n: usize| {
+		if connectivity.wrap {
+			(x + n).checked_add_signed(dx).map(|y| y % n)
+		} else {
+			x.checked_add_signed(dx).filter(|&y| y < n)
+		}
+	};
+
+	let offsets = if connectivity.diagonal {
+		&OFFSETS[..]
+	} else {
+		&OFFSETS[..4]
+	};
+
+	let mut neighbors = Vec::with_capacity(offsets.len());
+	for &[dr, dc] in offsets {
+		let (Some(r), Some(c)) = (step(row, dr, n_rows), step(col, dc, n_cols)) else {
+			continue;
+		};
+		// On grids only one or two cells across, wrapping can lead back to the cell itself
+		// or reach the same neighbor twice
+		if [r, c] != [row, col] && !neighbors.contains(&[r, c]) {
+			neighbors.push([r, c]);
+		}
+	}
+	neighbors
 }
 
 impl Heightmap {
-	fn from_str(s: &str) -> Option<Self> {
-		let width = s.lines().next()?.chars().count();
+	pub fn parse(s: &str) -> Option<Self> {
+		let width = s.lines().next()?.trim().chars().count();
 		let height = s.lines().count();
 
 		let mut data = Vec::new();
@@ -36,13 +79,29 @@ impl Heightmap {
 		}
 		let data = data.iter().copied().collect::<Option<Vec<_>>>()?;
 
-		let arr = Array2::from_shape_vec((width, height), data).ok()?;
-		Some(Self { arr })
+		let arr = Array2::from_shape_vec((height, width), data).ok()?;
+		Some(Self {
+			arr,
+			connectivity: Connectivity::default(),
+		})
+	}
+
+	#[must_use]
+	pub fn with_connectivity(self, connectivity: Connectivity) -> Self {
+		Self {
+			connectivity,
+			..self
+		}
+	}
+
+	fn neighbors(&self, idx: [usize; 2]) -> Vec<[usize; 2]> {
+		let (n_rows, n_cols) = self.arr.dim();
+		get_neighbor_idxs(idx, n_rows, n_cols, self.connectivity)
 	}
 }
 
 fn ans_for_input(input: &str) -> Answer<usize, usize> {
-	let hm = Heightmap::from_str(input).unwrap();
+	let hm = Heightmap::parse(input).unwrap();
 	(9, (pt1(&hm), pt2(&hm))).into()
 }
 
@@ -55,14 +114,8 @@ pub fn ans() -> Answer<usize, usize> {
 impl Heightmap {
 	fn is_lower_than_neighbors(&self, idx: [usize; 2]) -> bool {
 		let val = self.arr[idx];
-		let (n_rows, n_cols) = self.arr.dim();
 
-		let neighbors = get_neighbor_idxs(idx, n_rows, n_cols);
-		for idx_pair in neighbors {
-			let idxs = match idx_pair {
-				[Some(r), Some(c)] => [r, c],
-				_ => continue,
-			};
+		for idxs in self.neighbors(idx) {
 			if self.arr[idxs] <= val {
 				return false;
 			}
@@ -116,13 +169,7 @@ impl Heightmap {
 					continue;
 				}
 
-				let neighbor_idxs = get_neighbor_idxs(idx, n_rows, n_cols);
-
-				for nghbr_idx in neighbor_idxs {
-					let nghbr_idx = match nghbr_idx {
-						[Some(r), Some(c)] => [r, c],
-						_ => continue,
-					};
+				for nghbr_idx in self.neighbors(idx) {
 					if self.arr[nghbr_idx] != 9 && !visited_idxs.contains(&nghbr_idx) {
 						coords_stack.push(nghbr_idx);
 					}
@@ -146,6 +193,195 @@ fn pt2(hm: &Heightmap) -> usize {
 }
 // end::pt2[]
 
+// tag::labels[]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Basin {
+	/// The basin's lowest cell (the first in reading order, if there's a tie)
+	pub low_point: [usize; 2],
+	/// The basin's cells, in reading order
+	pub cells: Vec<[usize; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Basins {
+	/// The index into `basins` of the basin each cell belongs to, or `None` for walls
+	pub labels: Array2<Option<usize>>,
+	pub basins: Vec<Basin>,
+}
+
+impl Heightmap {
+	fn basins_from_labels(&self, labels: Array2<Option<usize>>, n_basins: usize) -> Basins {
+		let mut basins = vec![Vec::new(); n_basins];
+		for ((r, c), &label) in labels.indexed_iter() {
+			if let Some(label) = label {
+				basins[label].push([r, c]);
+			}
+		}
+
+		let basins = basins
+			.into_iter()
+			.map(|cells| Basin {
+				low_point: *cells.iter().min_by_key(|&&idx| self.arr[idx]).unwrap(),
+				cells,
+			})
+			.collect();
+		Basins { labels, basins }
+	}
+
+	/// Labels each of the regions walled off by 9s (as in part 2) as a basin
+	pub fn walled_basins(&self) -> Basins {
+		let mut labels = Array2::from_elem(self.arr.dim(), None);
+		let mut n_basins = 0;
+
+		for ((r, c), &height) in self.arr.indexed_iter() {
+			if height == 9 || labels[[r, c]].is_some() {
+				continue;
+			}
+			labels[[r, c]] = Some(n_basins);
+			let mut coords_stack = vec![[r, c]];
+			while let Some(idx) = coords_stack.pop() {
+				for nghbr_idx in self.neighbors(idx) {
+					if self.arr[nghbr_idx] != 9 && labels[nghbr_idx].is_none() {
+						labels[nghbr_idx] = Some(n_basins);
+						coords_stack.push(nghbr_idx);
+					}
+				}
+			}
+			n_basins += 1;
+		}
+
+		self.basins_from_labels(labels, n_basins)
+	}
+
+	/// For each cell, the neighbor that water flows to from it: its lowest neighbor, if
+	/// that's lower than it. Water on a flat stretch with a way down flows toward the
+	/// nearest such way down. Cells with nowhere to flow to (flat stretches included) are
+	/// the bottoms of basins, and get `None`
+	fn flows_to(&self) -> Array2<Option<[usize; 2]>> {
+		let mut flows_to = Array2::from_elem(self.arr.dim(), None);
+		let mut queue = VecDeque::new();
+
+		for ((r, c), &height) in self.arr.indexed_iter() {
+			let lowest = self
+				.neighbors([r, c])
+				.into_iter()
+				.min_by_key(|&idx| self.arr[idx]);
+			if let Some(lowest) = lowest.filter(|&idx| self.arr[idx] < height) {
+				flows_to[[r, c]] = Some(lowest);
+				queue.push_back([r, c]);
+			}
+		}
+
+		// A breadth-first search outward from each way down, along cells of the same height
+		while let Some(idx) = queue.pop_front() {
+			for nghbr_idx in self.neighbors(idx) {
+				if self.arr[nghbr_idx] == self.arr[idx] && flows_to[nghbr_idx].is_none() {
+					flows_to[nghbr_idx] = Some(idx);
+					queue.push_back(nghbr_idx);
+				}
+			}
+		}
+
+		flows_to
+	}
+
+	/// Labels every cell by the basin that water flows into from it, regardless of height.
+	/// Each basin's bottom is a single cell or a flat stretch of cells from which water
+	/// can't flow any lower
+	pub fn watershed(&self) -> Basins {
+		self.flow_graph().basins
+	}
+
+	/// The graph of where water flows from each cell, along with the basins it defines
+	pub fn flow_graph(&self) -> FlowGraph {
+		let flows_to = self.flows_to();
+		let mut labels = Array2::from_elem(self.arr.dim(), None);
+		let mut n_basins = 0;
+
+		for ((r, c), &target) in flows_to.indexed_iter() {
+			if target.is_some() || labels[[r, c]].is_some() {
+				continue;
+			}
+			labels[[r, c]] = Some(n_basins);
+			let mut coords_stack = vec![[r, c]];
+			while let Some(idx) = coords_stack.pop() {
+				for nghbr_idx in self.neighbors(idx) {
+					if flows_to[nghbr_idx].is_none() && labels[nghbr_idx].is_none() {
+						labels[nghbr_idx] = Some(n_basins);
+						coords_stack.push(nghbr_idx);
+					}
+				}
+			}
+			n_basins += 1;
+		}
+
+		for ((r, c), _) in self.arr.indexed_iter() {
+			let mut path = Vec::new();
+			let mut idx = [r, c];
+			let label = loop {
+				if let Some(label) = labels[idx] {
+					break label;
+				}
+				path.push(idx);
+				idx = flows_to[idx].unwrap();
+			};
+			for idx in path {
+				labels[idx] = Some(label);
+			}
+		}
+
+		FlowGraph {
+			basins: self.basins_from_labels(labels, n_basins),
+			flows_to,
+		}
+	}
+}
+
+impl std::fmt::Display for Basins {
+	/// Draws each basin with its own symbol (repeating after 62 basins), and walls as `#`
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		const SYMBOLS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+		for row in self.labels.rows() {
+			for &label in row {
+				let symbol = label.map_or(b'#', |label| SYMBOLS[label % SYMBOLS.len()]);
+				write!(f, "{}", char::from(symbol))?;
+			}
+			writeln!(f)?;
+		}
+		Ok(())
+	}
+}
+// end::labels[]
+
+// tag::flow[]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowGraph {
+	/// The neighbor that water flows to from each cell, or `None` at the bottom of a basin
+	pub flows_to: Array2<Option<[usize; 2]>>,
+	/// The basins of the watershed, which every cell belongs to one of
+	pub basins: Basins,
+}
+
+impl FlowGraph {
+	/// The low point of the basin that water from `idx` ends up in
+	pub fn drains_into(&self, idx: [usize; 2]) -> Option<[usize; 2]> {
+		let label = (*self.basins.labels.get(idx)?)?;
+		Some(self.basins.basins[label].low_point)
+	}
+
+	/// The number of cells whose water ends up at each low point, including the low point
+	/// itself
+	pub fn catchment_sizes(&self) -> Map<[usize; 2], usize> {
+		self.basins
+			.basins
+			.iter()
+			.map(|basin| (basin.low_point, basin.cells.len()))
+			.collect()
+	}
+}
+// end::flow[]
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -155,4 +391,136 @@ mod test {
 	fn test() {
 		test_input!(include_str!("input.txt"), day: 9, ans: (575, 1_019_700));
 	}
+
+	#[test]
+	fn test_rectangular() {
+		let hm = Heightmap::parse(include_str!("sample_input.txt")).unwrap();
+		assert_eq!(hm.arr.dim(), (5, 10));
+		assert_eq!(hm.arr[[0, 9]], 0);
+		assert_eq!((pt1(&hm), pt2(&hm)), (15, 1134));
+
+		let tall = Heightmap::parse("19\n99\n90\n91\n89").unwrap();
+		assert_eq!(tall.arr.dim(), (5, 2));
+		assert_eq!(
+			tall.idxs_where_lower_than_neighbors(),
+			vec![[0, 0], [2, 1], [4, 0]]
+		);
+	}
+
+	#[test]
+	fn test_labels() {
+		let hm = Heightmap::parse(include_str!("sample_input.txt")).unwrap();
+		let walled = hm.walled_basins();
+		assert_eq!(
+			walled
+				.basins
+				.iter()
+				.map(|b| b.low_point)
+				.collect::<Vec<_>>(),
+			hm.idxs_where_lower_than_neighbors()
+		);
+		let mut sizes = walled
+			.basins
+			.iter()
+			.map(|b| b.cells.len())
+			.collect::<Vec<_>>();
+		let mut expected_sizes = hm.basin_sizes();
+		sizes.sort_unstable();
+		expected_sizes.sort_unstable();
+		assert_eq!(sizes, expected_sizes);
+		assert_eq!(
+			walled.to_string(),
+			"aa###bbbbb\na#ccc#b#bb\n#ccccc#d#b\nccccc#ddd#\n#c###ddddd\n"
+		);
+
+		// Without walls, the 9s drain into the basins too
+		let watershed = hm.watershed();
+		assert_eq!(
+			watershed
+				.basins
+				.iter()
+				.map(|b| b.low_point)
+				.collect::<Vec<_>>(),
+			hm.idxs_where_lower_than_neighbors()
+		);
+		assert!(watershed.labels.iter().all(Option::is_some));
+		assert_eq!(
+			watershed
+				.basins
+				.iter()
+				.map(|b| b.cells.len())
+				.sum::<usize>(),
+			hm.arr.len()
+		);
+
+		// Flat stretches: the 5 at the top has no lower neighbor but drains through the other
+		// 5s, and the two 2s together are the bottom of their basin
+		let hm = Heightmap::parse("22955\n99951").unwrap();
+		let watershed = hm.watershed();
+		assert_eq!(watershed.to_string(), "aaabb\naabbb\n");
+		assert_eq!(watershed.basins[0].low_point, [0, 0]);
+		assert_eq!(
+			watershed.basins[0].cells,
+			vec![[0, 0], [0, 1], [0, 2], [1, 0], [1, 1]]
+		);
+		assert_eq!(watershed.basins[1].low_point, [1, 4]);
+	}
+
+	#[test]
+	fn test_connectivity() {
+		let eight = Connectivity {
+			diagonal: true,
+			wrap: false,
+		};
+		let four_wrapping = Connectivity {
+			diagonal: false,
+			wrap: true,
+		};
+
+		assert_eq!(
+			get_neighbor_idxs([0, 0], 3, 3, eight),
+			vec![[1, 0], [0, 1], [1, 1]]
+		);
+		assert_eq!(
+			get_neighbor_idxs([0, 0], 3, 3, four_wrapping),
+			vec![[1, 0], [2, 0], [0, 1], [0, 2]]
+		);
+		// Both ways around a two-cell row lead to the same neighbor
+		assert_eq!(get_neighbor_idxs([0, 0], 1, 2, four_wrapping), vec![[0, 1]]);
+
+		// Diagonals join the two 1s into one basin, whose low point is the lower of them
+		let hm = Heightmap::parse("19\n92").unwrap();
+		assert_eq!(hm.idxs_where_lower_than_neighbors(), vec![[0, 0], [1, 1]]);
+		assert_eq!(hm.walled_basins().basins.len(), 2);
+		let hm = hm.with_connectivity(eight);
+		assert_eq!(hm.idxs_where_lower_than_neighbors(), vec![[0, 0]]);
+		assert_eq!(hm.walled_basins().to_string(), "a#\n#a\n");
+
+		// Wrapping around, the 3 is next to the 0
+		let hm = Heightmap::parse("0123").unwrap();
+		assert_eq!(hm.flow_graph().flows_to[[0, 3]], Some([0, 2]));
+		let hm = hm.with_connectivity(four_wrapping);
+		assert_eq!(hm.flow_graph().flows_to[[0, 3]], Some([0, 0]));
+
+		let hm = Heightmap::parse(include_str!("sample_input.txt")).unwrap();
+		let graph = hm.flow_graph();
+		assert_eq!(graph.drains_into([1, 0]), Some([0, 1]));
+		assert_eq!(graph.drains_into([0, 4]), Some([0, 9]));
+		assert_eq!(graph.drains_into([4, 0]), Some([2, 2]));
+		assert_eq!(graph.drains_into([5, 0]), None);
+		let catchments = graph.catchment_sizes();
+		assert_eq!(
+			catchments.keys().copied().collect::<Vec<_>>(),
+			hm.idxs_where_lower_than_neighbors()
+		);
+		assert_eq!(catchments.values().sum::<usize>(), 50);
+
+		// Every cell flows to a lower neighbor, or along a flat stretch
+		for ((r, c), &target) in graph.flows_to.indexed_iter() {
+			if let Some(target) = target {
+				assert!(hm.neighbors([r, c]).contains(&target));
+				assert!(hm.arr[target] <= hm.arr[[r, c]]);
+			}
+		}
+	}
 }
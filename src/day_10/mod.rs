@@ -1,16 +1,12 @@
 // tag::setup[]
 use crate::Answer;
 
+/// Which of a grammar's bracket pairs a delimiter belongs to
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Brace {
-	Paren,
-	Square,
-	Curly,
-	Angle,
-}
+pub struct Brace(usize);
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Orientation {
+pub enum Orientation {
 	Left,
 	Right,
 }
@@ -26,30 +22,12 @@ impl Orientation {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct Token {
+pub struct Token {
 	brace: Brace,
 	orientation: Orientation,
 }
 
 impl Token {
-	fn from_char(c: char) -> Option<Self> {
-		use Brace::*;
-		use Orientation::*;
-
-		let (orientation, brace) = match c {
-			'(' => (Left, Paren),
-			')' => (Right, Paren),
-			'[' => (Left, Square),
-			']' => (Right, Square),
-			'{' => (Left, Curly),
-			'}' => (Right, Curly),
-			'<' => (Left, Angle),
-			'>' => (Right, Angle),
-			_ => return None,
-		};
-
-		Some(Self { brace, orientation })
-	}
 	fn flip(self) -> Self {
 		Self {
 			orientation: self.orientation.flip(),
@@ -58,59 +36,151 @@ impl Token {
 	}
 }
 
-enum TokenizationErr {
-	Corrupted(Token),
+/// The bracket pairs that must be balanced. Delimiters may be any nonempty strings, as long
+/// as they're all distinct
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grammar {
+	pairs: Vec<[String; 2]>,
+	/// Every delimiter and its token, longest first, so that the longest delimiter wins
+	delimiters: Vec<(String, Token)>,
+}
+
+impl Default for Grammar {
+	fn default() -> Self {
+		Self::new([("(", ")"), ("[", "]"), ("{", "}"), ("<", ">")]).unwrap()
+	}
+}
+
+impl Grammar {
+	pub fn new<S: Into<String>>(pairs: impl IntoIterator<Item = (S, S)>) -> Option<Self> {
+		use Orientation::*;
+
+		let pairs = pairs
+			.into_iter()
+			.map(|(left, right)| [left.into(), right.into()])
+			.collect::<Vec<_>>();
+
+		let mut delimiters = Vec::new();
+		for (i, [left, right]) in pairs.iter().enumerate() {
+			for (delim, orientation) in [(left, Left), (right, Right)] {
+				if delim.is_empty() || delimiters.iter().any(|(d, _)| d == delim) {
+					return None;
+				}
+				let token = Token {
+					brace: Brace(i),
+					orientation,
+				};
+				delimiters.push((delim.clone(), token));
+			}
+		}
+		delimiters.sort_by_key(|(d, _)| std::cmp::Reverse(d.len()));
+
+		Some(Self { pairs, delimiters })
+	}
+
+	pub fn delimiter(&self, token: Token) -> &str {
+		let [left, right] = &self.pairs[token.brace.0];
+		match token.orientation {
+			Orientation::Left => left,
+			Orientation::Right => right,
+		}
+	}
+
+	/// Splits `line` into its delimiters, each with the (zero-based, in `char`s) column it
+	/// starts at. Any other text is skipped if `skip_other` is set, and is an error if not
+	fn tokenize(&self, line: &str, skip_other: bool) -> Option<Vec<(usize, Token)>> {
+		let mut tokens = Vec::new();
+		let mut rest = line;
+		let mut column = 0;
+		while let Some(c) = rest.chars().next() {
+			let (len, n_chars) = match self.delimiters.iter().find(|(d, _)| rest.starts_with(d)) {
+				Some((delim, token)) => {
+					tokens.push((column, *token));
+					(delim.len(), delim.chars().count())
+				}
+				None if skip_other => (c.len_utf8(), 1),
+				None => return None,
+			};
+			rest = &rest[len..];
+			column += n_chars;
+		}
+		Some(tokens)
+	}
+}
+
+/// What was expected where a line went wrong: the closer for the innermost open opener,
+/// and that opener's column
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Expected {
+	pub closer: Token,
+	pub opener_column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenizationErr {
+	/// A closer at `column` that doesn't close the innermost open opener, or (if there's
+	/// nothing to expect) closes nothing at all
+	Corrupted {
+		column: usize,
+		found: Token,
+		expected: Option<Expected>,
+	},
+	/// The closers that would complete the line, in order
 	Incomplete(Vec<Token>),
 }
 
 type ParseResult = Result<(), TokenizationErr>;
 
-fn parse_line<T: std::borrow::Borrow<Token>>(line: impl Iterator<Item = T>) -> ParseResult {
-	use Orientation::*;
+/// Pushes an opener onto the stack, or pops the opener that a closer closes and returns it
+fn push_token(
+	token_stack: &mut Vec<(usize, Token)>,
+	(column, curr): (usize, Token),
+) -> Result<Option<(usize, Token)>, TokenizationErr> {
+	if curr.orientation == Orientation::Left {
+		token_stack.push((column, curr));
+		return Ok(None);
+	}
+	match token_stack.last() {
+		Some(&(_, prev)) if prev.brace == curr.brace => Ok(token_stack.pop()),
+		prev => Err(TokenizationErr::Corrupted {
+			column,
+			found: curr,
+			expected: prev.map(|&(opener_column, prev)| Expected {
+				closer: prev.flip(),
+				opener_column,
+			}),
+		}),
+	}
+}
+
+fn closers_for(token_stack: &[(usize, Token)]) -> Vec<Token> {
+	token_stack.iter().rev().map(|(_, t)| t.flip()).collect()
+}
 
+fn parse_line<T: std::borrow::Borrow<(usize, Token)>>(
+	line: impl Iterator<Item = T>,
+) -> ParseResult {
 	let mut token_stack = Vec::new();
 	for curr in line {
-		let curr = *curr.borrow();
-		match token_stack.last() {
-			None => {
-				token_stack.push(curr);
-			}
-			Some(&prev) => {
-				if prev.orientation == Left && curr.orientation == Right {
-					if prev.brace != curr.brace {
-						return Err(TokenizationErr::Corrupted(curr));
-					}
-					token_stack.pop();
-				} else {
-					token_stack.push(curr);
-				}
-			}
-		}
+		push_token(&mut token_stack, *curr.borrow())?;
 	}
 
 	if !token_stack.is_empty() {
-		return Err(TokenizationErr::Incomplete(
-			token_stack.iter().rev().map(|t| t.flip()).collect(),
-		));
+		return Err(TokenizationErr::Incomplete(closers_for(&token_stack)));
 	}
 
 	Ok(())
 }
 
-fn read_input(input: &str) -> Option<Vec<Vec<Token>>> {
+fn read_input(grammar: &Grammar, input: &str) -> Option<Vec<Vec<(usize, Token)>>> {
 	input
 		.lines()
-		.map(|line| {
-			line.trim()
-				.chars()
-				.map(Token::from_char)
-				.collect::<Option<Vec<_>>>()
-		})
+		.map(|line| grammar.tokenize(line.trim(), false))
 		.collect::<Option<Vec<_>>>()
 }
 
 fn ans_for_input(input: &str) -> Answer<usize, usize> {
-	let tokens = read_input(input).unwrap();
+	let tokens = read_input(&Grammar::default(), input).unwrap();
 	let parsed_lines = tokens
 		.iter()
 		.map(|v| parse_line(v.iter()))
@@ -125,16 +195,10 @@ pub fn ans() -> Answer<usize, usize> {
 
 // tag::pt1[]
 fn pt1<P: std::borrow::Borrow<ParseResult>>(prs: impl Iterator<Item = P>) -> usize {
-	use Brace::*;
 	prs.filter_map(|r| {
 		let r = r.borrow();
-		if let Err(TokenizationErr::Corrupted(t)) = r {
-			Some(match t.brace {
-				Paren => 3,
-				Square => 57,
-				Curly => 1197,
-				Angle => 25137,
-			})
+		if let Err(TokenizationErr::Corrupted { found, .. }) = r {
+			Some([3, 57, 1197, 25137][found.brace.0])
 		} else {
 			None
 		}
@@ -145,7 +209,6 @@ fn pt1<P: std::borrow::Borrow<ParseResult>>(prs: impl Iterator<Item = P>) -> usi
 
 // tag::pt2[]
 fn pt2<P: std::borrow::Borrow<ParseResult>>(prs: impl Iterator<Item = P>) -> usize {
-	use Brace::*;
 	let mut scores = prs
 		.filter_map(|r| {
 			let r = r.borrow();
@@ -153,13 +216,7 @@ fn pt2<P: std::borrow::Borrow<ParseResult>>(prs: impl Iterator<Item = P>) -> usi
 				let mut score = 0_usize;
 				for t in tokens {
 					score *= 5;
-					let token_score = match t.brace {
-						Paren => 1,
-						Square => 2,
-						Curly => 3,
-						Angle => 4,
-					};
-					score += token_score;
+					score += t.brace.0 + 1;
 				}
 				Some(score)
 			} else {
@@ -173,6 +230,482 @@ fn pt2<P: std::borrow::Borrow<ParseResult>>(prs: impl Iterator<Item = P>) -> usi
 }
 // end::pt2[]
 
+// tag::lint[]
+impl Grammar {
+	/// Describes what's wrong with a line, with one-based columns
+	pub fn describe(&self, err: &TokenizationErr) -> String {
+		match err {
+			TokenizationErr::Corrupted {
+				column,
+				found,
+				expected: Some(Expected {
+					closer,
+					opener_column,
+				}),
+			} => {
+				let (column, opener_column) = (column + 1, opener_column + 1);
+				let opener = self.delimiter(closer.flip());
+				let (found, closer) = (self.delimiter(*found), self.delimiter(*closer));
+				format!(
+					"column {column}: expected `{closer}` to close `{opener}` from column \
+					 {opener_column}, found `{found}`"
+				)
+			}
+			TokenizationErr::Corrupted {
+				column,
+				found,
+				expected: None,
+			} => {
+				let column = column + 1;
+				format!(
+					"column {column}: `{}` closes nothing",
+					self.delimiter(*found)
+				)
+			}
+			TokenizationErr::Incomplete(closers) => {
+				let completion = closers
+					.iter()
+					.map(|&t| self.delimiter(t))
+					.collect::<String>();
+				format!("end of line: unclosed, needs `{completion}`")
+			}
+		}
+	}
+}
+
+/// Checks that the delimiters in each line of `text` are balanced, ignoring everything
+/// else, and describes each problem, prefixed with its one-based line number
+pub fn lint(grammar: &Grammar, text: &str) -> Vec<String> {
+	text.lines()
+		.enumerate()
+		.filter_map(|(i, line)| {
+			let tokens = grammar.tokenize(line, true)?;
+			let err = parse_line(tokens.iter()).err()?;
+			Some(format!("line {}, {}", i + 1, grammar.describe(&err)))
+		})
+		.collect()
+}
+// end::lint[]
+
+// tag::repair[]
+/// One change to a line. Columns are those of the original line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit {
+	/// Insert `token` before the delimiter at `column` (or at the end of the line)
+	Insert {
+		column: usize,
+		token: Token,
+	},
+	Delete {
+		column: usize,
+	},
+	Substitute {
+		column: usize,
+		token: Token,
+	},
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repair {
+	/// The line, no longer corrupted (but possibly incomplete)
+	pub line: String,
+	/// The edits that turn the original line into `line`, from left to right
+	pub edits: Vec<Edit>,
+	/// The closers that complete `line`, as for any incomplete line
+	pub completion: String,
+}
+
+/// The most delimiters a line may have for `Grammar::repair_line` to repair it; the
+/// repair takes time cubic, and memory quadratic, in the number of delimiters
+pub const MAX_REPAIR_LEN: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepairErr {
+	/// The line has a character that isn't a delimiter
+	NotDelimiters,
+	/// The line has `n_tokens` delimiters, more than `MAX_REPAIR_LEN`
+	TooLong { n_tokens: usize },
+}
+
+#[derive(Clone, Copy)]
+enum Choice {
+	/// The first token is left open (if an opener), or deleted (if a closer). In a balanced
+	/// stretch, an opener is instead closed at the stretch's end
+	Lone,
+	/// The first token is matched with the token at this index
+	Match(usize),
+}
+
+/// The substitutions needed to make `left` and `right` a matching pair, keeping whichever
+/// brace needs fewer of them (`left`'s, if it doesn't matter)
+fn pair_edits(left: Token, right: Token) -> [Option<Token>; 2] {
+	use Orientation::*;
+	let brace = if left.orientation == Right && right.orientation == Right {
+		right.brace
+	} else {
+		left.brace
+	};
+	let opener = Token {
+		brace,
+		orientation: Left,
+	};
+	let closer = opener.flip();
+	[
+		(left != opener).then_some(opener),
+		(right != closer).then_some(closer),
+	]
+}
+
+/// Finds a sequence of tokens without any mismatched closers (so, balanced except perhaps
+/// for some openers left open) as few insertions, deletions, and substitutions away from
+/// `tokens` as possible. `columns` holds each token's column, followed by the column just
+/// past the end of the line. For `n` tokens, this takes O(n^3) time and O(n^2) memory
+fn repair_tokens(tokens: &[Token], columns: &[usize]) -> (Vec<Token>, Vec<Edit>) {
+	enum Work {
+		Balanced(usize, usize),
+		Prefix(usize),
+		Emit(Token, Option<Edit>),
+	}
+
+	let n = tokens.len();
+	let n_subs = |i: usize, k: usize| pair_edits(tokens[i], tokens[k]).iter().flatten().count();
+
+	// balanced[i][j] is the fewest edits to balance tokens[i..j]
+	let mut balanced = vec![vec![(0, Choice::Lone); n + 1]; n + 1];
+	for len in 1..=n {
+		for i in 0..=n - len {
+			let j = i + len;
+			let mut best = (1 + balanced[i + 1][j].0, Choice::Lone);
+			for k in i + 1..j {
+				let total = n_subs(i, k) + balanced[i + 1][k].0 + balanced[k + 1][j].0;
+				if total < best.0 {
+					best = (total, Choice::Match(k));
+				}
+			}
+			balanced[i][j] = best;
+		}
+	}
+
+	// prefix[i] is the fewest edits to rid tokens[i..] of mismatched closers
+	let mut prefix = vec![(0, Choice::Lone); n + 1];
+	for i in (0..n).rev() {
+		let lone_cost = usize::from(tokens[i].orientation == Orientation::Right);
+		let mut best = (lone_cost + prefix[i + 1].0, Choice::Lone);
+		for k in i + 1..n {
+			let total = n_subs(i, k) + balanced[i + 1][k].0 + prefix[k + 1].0;
+			if total < best.0 {
+				best = (total, Choice::Match(k));
+			}
+		}
+		prefix[i] = best;
+	}
+
+	let mut repaired = Vec::new();
+	let mut edits = Vec::new();
+	// What's still to be repaired, or emitted (with any edit), last first
+	let mut work = vec![Work::Prefix(0)];
+	while let Some(item) = work.pop() {
+		let (i, end, choice) = match item {
+			Work::Balanced(i, j) if i < j => (i, Some(j), balanced[i][j].1),
+			Work::Prefix(i) if i < n => (i, None, prefix[i].1),
+			Work::Balanced(..) | Work::Prefix(_) => continue,
+			Work::Emit(token, edit) => {
+				repaired.push(token);
+				edits.extend(edit);
+				continue;
+			}
+		};
+		let rest = |from| end.map_or(Work::Prefix(from), |j| Work::Balanced(from, j));
+
+		let token = tokens[i];
+		match choice {
+			Choice::Lone if token.orientation == Orientation::Right => {
+				edits.push(Edit::Delete { column: columns[i] });
+				work.push(rest(i + 1));
+			}
+			Choice::Lone => {
+				// An opener in a balanced stretch gets its closer at the stretch's end
+				if let Some(j) = end {
+					let closer = token.flip();
+					let insertion = Edit::Insert {
+						column: columns[j],
+						token: closer,
+					};
+					work.push(Work::Emit(closer, Some(insertion)));
+				}
+				work.push(rest(i + 1));
+				repaired.push(token);
+			}
+			Choice::Match(k) => {
+				let [left, right] = pair_edits(token, tokens[k]);
+				work.push(rest(k + 1));
+				work.push(Work::Emit(
+					right.unwrap_or(tokens[k]),
+					right.map(|token| Edit::Substitute {
+						column: columns[k],
+						token,
+					}),
+				));
+				work.push(Work::Balanced(i + 1, k));
+				repaired.push(left.unwrap_or(token));
+				edits.extend(left.map(|token| Edit::Substitute {
+					column: columns[i],
+					token,
+				}));
+			}
+		}
+	}
+
+	(repaired, edits)
+}
+
+impl Grammar {
+	/// Fixes a line made up entirely of delimiters with as few edits as possible, so that it's
+	/// no longer corrupted, and completes it. Incomplete lines need no edits
+	///
+	/// # Errors
+	///
+	/// If the line has anything but delimiters, or more than `MAX_REPAIR_LEN` of them
+	pub fn repair_line(&self, line: &str) -> Result<Repair, RepairErr> {
+		let (columns, tokens): (Vec<_>, Vec<_>) = self
+			.tokenize(line, false)
+			.ok_or(RepairErr::NotDelimiters)?
+			.into_iter()
+			.unzip();
+		if tokens.len() > MAX_REPAIR_LEN {
+			return Err(RepairErr::TooLong {
+				n_tokens: tokens.len(),
+			});
+		}
+		let columns = [&columns[..], &[line.chars().count()]].concat();
+		let (repaired, edits) = repair_tokens(&tokens, &columns);
+
+		let completion = match parse_line(repaired.iter().copied().enumerate()) {
+			Ok(()) => Vec::new(),
+			Err(TokenizationErr::Incomplete(completion)) => completion,
+			Err(TokenizationErr::Corrupted { .. }) => unreachable!("the line was repaired"),
+		};
+		Ok(Repair {
+			line: repaired.into_iter().map(|t| self.delimiter(t)).collect(),
+			edits,
+			completion: completion.into_iter().map(|t| self.delimiter(t)).collect(),
+		})
+	}
+}
+// end::repair[]
+
+// tag::validator[]
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum StackOp {
+	Push,
+	Pop((usize, Token)),
+}
+
+/// How to take back one typed character
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct UndoEntry {
+	c: char,
+	pending: String,
+	stack_ops: Vec<StackOp>,
+	corrupted_here: bool,
+}
+
+/// Checks a line as it's typed, one character or chunk at a time, skipping anything that
+/// isn't a delimiter
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Validator<'a> {
+	grammar: &'a Grammar,
+	n_typed: usize,
+	/// The typed characters not yet tokenized, because they might still turn out to be the
+	/// start of a longer delimiter
+	pending: String,
+	token_stack: Vec<(usize, Token)>,
+	corruption: Option<TokenizationErr>,
+	undo_log: Vec<UndoEntry>,
+}
+
+impl<'a> Validator<'a> {
+	pub fn new(grammar: &'a Grammar) -> Self {
+		Self {
+			grammar,
+			n_typed: 0,
+			pending: String::new(),
+			token_stack: Vec::new(),
+			corruption: None,
+			undo_log: Vec::new(),
+		}
+	}
+
+	/// Takes the next token, with its column, off the front of `pending` (the last
+	/// characters of the `n_typed` typed so far), skipping anything that isn't a
+	/// delimiter. Unless `at_end`, stops short of anything that's the start of a longer
+	/// delimiter
+	fn take_pending_token(
+		&self,
+		pending: &mut String,
+		n_typed: usize,
+		at_end: bool,
+	) -> Option<(usize, Token)> {
+		while !pending.is_empty() {
+			let could_grow = self
+				.grammar
+				.delimiters
+				.iter()
+				.any(|(d, _)| d.len() > pending.len() && d.starts_with(pending.as_str()));
+			if could_grow && !at_end {
+				return None;
+			}
+
+			let column = n_typed - pending.chars().count();
+			let delim = self
+				.grammar
+				.delimiters
+				.iter()
+				.find(|(d, _)| pending.starts_with(d.as_str()));
+			let Some((delim, token)) = delim else {
+				pending.remove(0);
+				continue;
+			};
+			pending.drain(..delim.len());
+			return Some((column, *token));
+		}
+		None
+	}
+
+	/// Tokenizes as much of `pending` as can be
+	fn drain_pending(&mut self, stack_ops: &mut Vec<StackOp>) {
+		let mut pending = std::mem::take(&mut self.pending);
+		while self.corruption.is_none() {
+			let Some(token) = self.take_pending_token(&mut pending, self.n_typed, false) else {
+				break;
+			};
+			match push_token(&mut self.token_stack, token) {
+				Ok(None) => stack_ops.push(StackOp::Push),
+				Ok(Some(popped)) => stack_ops.push(StackOp::Pop(popped)),
+				Err(err) => self.corruption = Some(err),
+			}
+		}
+		self.pending = pending;
+	}
+
+	pub fn push(&mut self, c: char) {
+		let pending = self.pending.clone();
+		let was_corrupted = self.is_corrupted();
+		let mut stack_ops = Vec::new();
+
+		self.n_typed += 1;
+		if !was_corrupted {
+			self.pending.push(c);
+			self.drain_pending(&mut stack_ops);
+		}
+
+		self.undo_log.push(UndoEntry {
+			c,
+			pending,
+			stack_ops,
+			corrupted_here: !was_corrupted && self.is_corrupted(),
+		});
+	}
+
+	pub fn push_str(&mut self, chunk: &str) {
+		for c in chunk.chars() {
+			self.push(c);
+		}
+	}
+
+	/// Takes back the last character typed, returning it
+	pub fn undo(&mut self) -> Option<char> {
+		let UndoEntry {
+			c,
+			pending,
+			stack_ops,
+			corrupted_here,
+		} = self.undo_log.pop()?;
+
+		for op in stack_ops.into_iter().rev() {
+			match op {
+				StackOp::Push => {
+					self.token_stack.pop();
+				}
+				StackOp::Pop(popped) => self.token_stack.push(popped),
+			}
+		}
+		if corrupted_here {
+			self.corruption = None;
+		}
+		self.pending = pending;
+		self.n_typed -= 1;
+
+		Some(c)
+	}
+
+	/// The openers not yet closed, with their columns, innermost last. This doesn't
+	/// include any delimiter still pending
+	pub fn open_stack(&self) -> &[(usize, Token)] {
+		&self.token_stack
+	}
+
+	/// Typed text that might be the start of a longer delimiter
+	pub fn pending(&self) -> &str {
+		&self.pending
+	}
+
+	pub fn is_corrupted(&self) -> bool {
+		self.corruption.is_some()
+	}
+
+	/// How the line stands if it ends here
+	///
+	/// # Errors
+	///
+	/// `Corrupted` if the line has gone wrong, and `Incomplete` (with the closers it still
+	/// needs) if it hasn't but isn't finished
+	pub fn result(&self) -> ParseResult {
+		if let Some(err) = &self.corruption {
+			return Err(err.clone());
+		}
+
+		// Finish tokenizing what's pending (at most one delimiter's worth) without touching
+		// `token_stack`: openers go on a stack of their own, and closers pop from that one
+		// first and then from the first `n_open` entries of `token_stack`
+		let mut pending = self.pending.clone();
+		let mut n_open = self.token_stack.len();
+		let mut opened = Vec::new();
+		while let Some(token) = self.take_pending_token(&mut pending, self.n_typed, true) {
+			if opened.is_empty() && n_open > 0 && token.1.orientation == Orientation::Right {
+				let mut innermost = vec![self.token_stack[n_open - 1]];
+				push_token(&mut innermost, token)?;
+				n_open -= 1;
+			} else {
+				push_token(&mut opened, token)?;
+			}
+		}
+
+		if n_open == 0 && opened.is_empty() {
+			Ok(())
+		} else {
+			Err(TokenizationErr::Incomplete(closers_for(
+				&[&self.token_stack[..n_open], &opened].concat(),
+			)))
+		}
+	}
+
+	/// The closers that complete the line if it ends here, unless it's corrupted
+	pub fn completion(&self) -> Option<String> {
+		match self.result() {
+			Ok(()) => Some(String::new()),
+			Err(TokenizationErr::Incomplete(closers)) => Some(
+				closers
+					.into_iter()
+					.map(|t| self.grammar.delimiter(t))
+					.collect(),
+			),
+			Err(TokenizationErr::Corrupted { .. }) => None,
+		}
+	}
+}
+// end::validator[]
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -182,4 +715,256 @@ mod test {
 	fn test() {
 		test_input!(include_str!("input.txt"), day: 10, ans: (167_379, 2_776_842_859));
 	}
+
+	#[test]
+	fn test_grammar() {
+		let aoc = Grammar::default();
+		let lines = read_input(&aoc, include_str!("sample_input.txt")).unwrap();
+		let err = parse_line(lines[2].iter()).unwrap_err();
+		assert_eq!(
+			aoc.describe(&err),
+			"column 13: expected `]` to close `[` from column 8, found `}`"
+		);
+		assert_eq!(read_input(&aoc, "(a)"), None);
+
+		let grammar =
+			Grammar::new([("(", ")"), ("«", "»"), ("/*", "*/"), ("begin", "end")]).unwrap();
+		let text = [
+			"fn f(x) { /* («ok») */ }",
+			"«nested (still» fine)",
+			"begin x := (1 + 2 end",
+			"trailing ) here",
+			"/* «unclosed ( */",
+		]
+		.join("\n");
+		assert_eq!(
+			lint(&grammar, &text),
+			vec![
+				"line 2, column 15: expected `)` to close `(` from column 9, found `»`",
+				"line 3, column 19: expected `)` to close `(` from column 12, found `end`",
+				"line 4, column 10: `)` closes nothing",
+				"line 5, column 16: expected `)` to close `(` from column 14, found `*/`",
+			]
+		);
+
+		let tokens = grammar.tokenize("/*«", false).unwrap();
+		assert_eq!(
+			tokens
+				.iter()
+				.map(|&(col, t)| (col, grammar.delimiter(t)))
+				.collect::<Vec<_>>(),
+			vec![(0, "/*"), (2, "«")]
+		);
+		assert_eq!(
+			parse_line(tokens.iter()),
+			Err(TokenizationErr::Incomplete(vec![
+				grammar.tokenize("»", false).unwrap()[0].1,
+				grammar.tokenize("*/", false).unwrap()[0].1,
+			]))
+		);
+
+		assert_eq!(Grammar::new([("(", ")"), ("[", "(")]), None);
+		assert_eq!(Grammar::new([("", ")")]), None);
+	}
+
+	#[test]
+	fn test_validator() {
+		let aoc = Grammar::default();
+		for line in include_str!("sample_input.txt").lines() {
+			let mut validator = Validator::new(&aoc);
+			validator.push_str(line);
+			let expected = parse_line(aoc.tokenize(line, false).unwrap().iter());
+			assert_eq!(validator.result(), expected);
+			assert_eq!(
+				validator.is_corrupted(),
+				matches!(expected, Err(TokenizationErr::Corrupted { .. }))
+			);
+		}
+
+		let mut validator = Validator::new(&aoc);
+		validator.push_str("[({(<(())[]>[[{[]{<()<>>");
+		assert_eq!(validator.completion().as_deref(), Some("}}]])})]"));
+		assert_eq!(validator.open_stack().len(), 8);
+		validator.push('}');
+		validator.push_str("}]])})");
+		assert_eq!(validator.completion().as_deref(), Some("]"));
+		validator.push('>');
+		assert!(validator.is_corrupted());
+		// Once corrupted, nothing more is checked until the corruption is undone
+		validator.push(']');
+		assert!(validator.is_corrupted());
+		assert_eq!(validator.undo(), Some(']'));
+		assert_eq!(validator.undo(), Some('>'));
+		assert!(!validator.is_corrupted());
+		validator.push(']');
+		assert_eq!(validator.result(), Ok(()));
+
+		// `<` might be the start of `<<`, and `*` of `*/`
+		let grammar = Grammar::new([("<", ">"), ("<<", ">>"), ("/*", "*/")]).unwrap();
+		let mut validator = Validator::new(&grammar);
+		validator.push_str("x <");
+		assert_eq!(
+			(validator.pending(), validator.open_stack()),
+			("<", &[][..])
+		);
+		assert_eq!(validator.completion().as_deref(), Some(">"));
+		validator.push('<');
+		assert_eq!(validator.pending(), "");
+		assert_eq!(
+			validator.open_stack(),
+			&grammar.tokenize("  <<", true).unwrap()[..]
+		);
+		assert_eq!(validator.completion().as_deref(), Some(">>"));
+		validator.push_str(" /* * ");
+		assert_eq!(validator.completion().as_deref(), Some("*/>>"));
+		validator.push_str("*/ >");
+		assert_eq!(validator.pending(), ">");
+		assert_eq!(validator.completion(), None);
+		validator.undo();
+		validator.push_str(">>");
+		assert_eq!(validator.result(), Ok(()));
+
+		// Undoing gets back to exactly where typing less would have
+		let text = "a<<b>/*<>*/<c>> >>d<</*e*/";
+		let mut rng = crate::utils::SplitMix64::new(49);
+		for _ in 0..100 {
+			let len = rng.below(text.len() + 1);
+			let n_undo = rng.below(len + 1);
+			let mut validator = Validator::new(&grammar);
+			validator.push_str(&text[..len]);
+			for _ in 0..n_undo {
+				validator.undo();
+			}
+			let mut expected = Validator::new(&grammar);
+			expected.push_str(&text[..len - n_undo]);
+			assert_eq!(validator, expected);
+		}
+		assert_eq!(Validator::new(&grammar).undo(), None);
+	}
+
+	#[test]
+	fn test_repair() {
+		use Orientation::*;
+
+		/// Every sequence of at most `max_len` tokens with braces below `n_braces` that has
+		/// no mismatched closers
+		fn uncorrupted(n_braces: usize, max_len: usize) -> Vec<Vec<Token>> {
+			let mut all = Vec::new();
+			let mut frontier = vec![(Vec::<Token>::new(), Vec::<Token>::new())];
+			while let Some((seq, stack)) = frontier.pop() {
+				if seq.len() < max_len {
+					for brace in 0..n_braces {
+						let opener = Token {
+							brace: Brace(brace),
+							orientation: Left,
+						};
+						let seq = [&seq[..], &[opener]].concat();
+						let stack = [&stack[..], &[opener]].concat();
+						frontier.push((seq, stack));
+					}
+					if let Some((&opener, rest)) = stack.split_last() {
+						frontier.push(([&seq[..], &[opener.flip()]].concat(), rest.to_vec()));
+					}
+				}
+				all.push(seq);
+			}
+			all
+		}
+
+		fn edit_distance(a: &[Token], b: &[Token]) -> usize {
+			let mut prev = (0..=b.len()).collect::<Vec<_>>();
+			for (i, x) in a.iter().enumerate() {
+				let mut curr = vec![i + 1];
+				for (j, y) in b.iter().enumerate() {
+					let substitution = prev[j] + usize::from(x != y);
+					curr.push(substitution.min(prev[j + 1] + 1).min(curr[j] + 1));
+				}
+				prev = curr;
+			}
+			prev[b.len()]
+		}
+
+		let aoc = Grammar::default();
+		let token = |s: &str| aoc.tokenize(s, false).unwrap()[0].1;
+		for line in include_str!("sample_input.txt").lines() {
+			let tokens = aoc.tokenize(line, false).unwrap();
+			let repair = aoc.repair_line(line).unwrap();
+			let completed = format!("{}{}", repair.line, repair.completion);
+			assert_eq!(
+				parse_line(aoc.tokenize(&completed, false).unwrap().iter()),
+				Ok(())
+			);
+			match parse_line(tokens.iter()).unwrap_err() {
+				TokenizationErr::Incomplete(_) => {
+					assert_eq!(repair.line, line);
+					assert_eq!(repair.edits, vec![]);
+				}
+				TokenizationErr::Corrupted { .. } => assert!(!repair.edits.is_empty()),
+			}
+		}
+
+		// One substitution suffices, although it's not the `]` that parsing expected
+		let repair = aoc.repair_line("{([(<{}[<>[]}>{[]{[(<()>").unwrap();
+		assert_eq!(repair.line, "{([(<{}[<>[]<>{[]{[(<()>");
+		assert_eq!(
+			repair.edits,
+			vec![Edit::Substitute {
+				column: 12,
+				token: token("<")
+			}]
+		);
+		assert_eq!(repair.completion, ")]}}]>)])}");
+
+		// Reopening with the `]` gives the extra `)` something to close
+		let repair = aoc.repair_line("(]))<").unwrap();
+		assert_eq!((&*repair.line, &*repair.completion), ("(())<", ">"));
+		assert_eq!(
+			repair.edits,
+			vec![Edit::Substitute {
+				column: 1,
+				token: token("(")
+			}]
+		);
+		let repair = aoc.repair_line("<)]").unwrap();
+		assert_eq!((&*repair.line, &*repair.completion), ("<[]", ">"));
+		// A stray closer is deleted, rather than turned into an opener
+		let repair = aoc.repair_line(")(").unwrap();
+		assert_eq!((&*repair.line, &*repair.completion), ("(", ")"));
+		assert_eq!(repair.edits, vec![Edit::Delete { column: 0 }]);
+
+		assert_eq!(aoc.repair_line("(a)"), Err(RepairErr::NotDelimiters));
+		assert_eq!(
+			aoc.repair_line(&"(".repeat(MAX_REPAIR_LEN + 1)),
+			Err(RepairErr::TooLong {
+				n_tokens: MAX_REPAIR_LEN + 1
+			})
+		);
+
+		// Check minimality against every uncorrupted sequence on short random lines. Each
+		// token can be deleted, so no line needs a longer fix than itself
+		let targets = uncorrupted(2, 6);
+		let mut rng = crate::utils::SplitMix64::new(10);
+		for _ in 0..300 {
+			let len = rng.below(7);
+			let tokens = (0..len)
+				.map(|_| Token {
+					brace: Brace(rng.below(2)),
+					orientation: if rng.below(2) == 0 { Left } else { Right },
+				})
+				.collect::<Vec<_>>();
+			let columns = (0..=len).collect::<Vec<_>>();
+			let (repaired, edits) = repair_tokens(&tokens, &columns);
+			let best = targets
+				.iter()
+				.map(|t| edit_distance(&tokens, t))
+				.min()
+				.unwrap();
+			assert_eq!(edits.len(), best);
+			assert_eq!(edit_distance(&tokens, &repaired), best);
+			assert!(!matches!(
+				parse_line(repaired.iter().copied().enumerate()),
+				Err(TokenizationErr::Corrupted { .. })
+			));
+		}
+	}
 }
@@ -1,16 +1,31 @@
 // tag::setup[]
 use crate::Answer;
+use hashbrown::HashMap;
 use ndarray::prelude::*;
 
 #[derive(Clone, Debug)]
-struct Octopi {
+pub struct Octopi {
 	arr: Array2<usize>,
 }
 
+/// The octopi that flashed during one step, grouped into waves: those in the first wave
+/// flashed from the step's increase alone, and those in each later wave flashed because of
+/// their neighbors in the wave before
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StepFlashes {
+	pub waves: Vec<Vec<[usize; 2]>>,
+}
+
+impl StepFlashes {
+	pub fn n_flashes(&self) -> usize {
+		self.waves.iter().map(Vec::len).sum()
+	}
+}
+
 impl Octopi {
-	fn from_str(s: &str) -> Option<Self> {
+	pub fn parse(s: &str) -> Option<Self> {
 		let mut data = Vec::new();
-		let width = s.lines().next()?.len();
+		let width = s.lines().next()?.trim().chars().count();
 		let height = s.lines().count();
 		for line in s.lines() {
 			data.extend(
@@ -20,11 +35,15 @@ impl Octopi {
 			);
 		}
 		let data = data.into_iter().collect::<Option<Vec<_>>>()?;
-		let arr = Array2::from_shape_vec((width, height), data).ok()?;
+		let arr = Array2::from_shape_vec((height, width), data).ok()?;
 		Some(Self { arr })
 	}
 
 	fn tick_in_place_and_count_flashes(&mut self) -> usize {
+		self.tick_in_place().n_flashes()
+	}
+
+	fn tick_in_place(&mut self) -> StepFlashes {
 		// We use equality with FLASH_THRESH to mean "will flash right now", whereas being
 		// greater than FLASH_THRESH means "has already flashed (and won't flash again)"
 		let flash_thresh = 10;
@@ -32,7 +51,7 @@ impl Octopi {
 		let (n_rows, n_cols) = self.arr.dim();
 
 		self.arr.mapv_inplace(|x| x + 1);
-		let mut n_flashes = 0;
+		let mut waves = Vec::new();
 
 		loop {
 			let flashing_octopi_idxs = (0..n_rows)
@@ -50,9 +69,7 @@ impl Octopi {
 				break;
 			}
 
-			n_flashes += flashing_octopi_idxs.len();
-
-			for [base_row, base_col] in flashing_octopi_idxs {
+			for &[base_row, base_col] in &flashing_octopi_idxs {
 				self.arr[[base_row, base_col]] += 1;
 
 				let rows = [
@@ -63,7 +80,7 @@ impl Octopi {
 				let cols = [
 					base_col.checked_sub(1),
 					Some(base_col),
-					(base_col + 1 < n_rows).then_some(base_col + 1),
+					(base_col + 1 < n_cols).then_some(base_col + 1),
 				];
 
 				for row in rows.into_iter().flatten() {
@@ -74,18 +91,21 @@ impl Octopi {
 					}
 				}
 			}
+
+			waves.push(flashing_octopi_idxs);
 		}
 
 		self.arr
 			.mapv_inplace(|val| if val >= flash_thresh { 0 } else { val });
 
-		n_flashes
+		StepFlashes { waves }
 	}
 }
 
 fn ans_for_input(input: &str) -> Answer<usize, usize> {
-	let octopi = Octopi::from_str(input).unwrap();
-	(11, (pt1(octopi.clone()), pt2(octopi))).into()
+	let octopi = Octopi::parse(input).unwrap();
+	let pt2 = pt2(octopi.clone()).expect("the octopi never all flash at once");
+	(11, (pt1(octopi), pt2)).into()
 }
 
 pub fn ans() -> Answer<usize, usize> {
@@ -108,16 +128,87 @@ fn pt1(octopi: Octopi) -> usize {
 // end::pt1[]
 
 // tag::pt2[]
-fn pt2(mut octopi: Octopi) -> usize {
-	let mut n = 0;
-	while octopi.arr.iter().any(|&val| val != 0) {
-		octopi.tick_in_place_and_count_flashes();
-		n += 1;
-	}
-	n
+fn pt2(octopi: Octopi) -> Option<usize> {
+	octopi.first_sync()
 }
 // end::pt2[]
 
+// tag::history[]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct History {
+	/// The flashes in each step, from the first step on, up to the first repeated state
+	pub steps: Vec<StepFlashes>,
+	/// The first number of steps after which every octopus is at 0, having just flashed
+	/// (or, for 0 steps, having started that way), if there is one
+	pub first_sync: Option<usize>,
+	/// The number of steps after which the octopi first reach a state they'll return to
+	pub cycle_start: usize,
+	/// How many steps it takes to return to that state
+	pub period: usize,
+}
+
+impl Octopi {
+	fn is_synced(&self) -> bool {
+		self.arr.iter().all(|&val| val == 0)
+	}
+
+	/// The first number of steps after which every octopus is at 0, as in
+	/// `History::first_sync`. Rather than remember every state to tell when the octopi
+	/// start repeating themselves, this uses Brent's cycle detection, which only keeps one
+	/// earlier state around and still sees every state in the cycle before it stops
+	pub fn first_sync(mut self) -> Option<usize> {
+		if self.is_synced() {
+			return Some(0);
+		}
+
+		let mut saved = self.arr.clone();
+		let (mut n_steps, mut n_since_saved, mut save_every) = (0, 0, 1);
+		loop {
+			self.tick_in_place();
+			n_steps += 1;
+			n_since_saved += 1;
+			if self.is_synced() {
+				return Some(n_steps);
+			}
+			if self.arr == saved {
+				return None;
+			}
+			if n_since_saved == save_every {
+				saved = self.arr.clone();
+				save_every *= 2;
+				n_since_saved = 0;
+			}
+		}
+	}
+
+	/// Steps the octopi until they repeat a state, after which they'll just keep repeating
+	/// the same cycle. So if they haven't all flashed at once by then, they never will
+	pub fn run_until_cycle(mut self) -> History {
+		let mut seen = HashMap::new();
+		let mut steps = Vec::new();
+		let mut first_sync = None;
+
+		loop {
+			let n_steps = steps.len();
+			if let Some(&cycle_start) = seen.get(&self.arr) {
+				return History {
+					steps,
+					first_sync,
+					cycle_start,
+					period: n_steps - cycle_start,
+				};
+			}
+			seen.insert(self.arr.clone(), n_steps);
+			if first_sync.is_none() && self.is_synced() {
+				first_sync = Some(n_steps);
+			}
+
+			steps.push(self.tick_in_place());
+		}
+	}
+}
+// end::history[]
+
 #[cfg(test)]
 mod test {
 	use super::*;
@@ -127,4 +218,120 @@ mod test {
 	fn test() {
 		test_input!(include_str!("input.txt"), day: 11, ans: (1617, 258));
 	}
+
+	#[test]
+	fn test_rectangular() {
+		let wide = "5483143\n2745854\n5264556";
+		let tall = "525\n472\n846\n354\n185\n455\n346";
+		let wide = Octopi::parse(wide).unwrap();
+		let tall = Octopi::parse(tall).unwrap();
+		assert_eq!((wide.arr.dim(), tall.arr.dim()), ((3, 7), (7, 3)));
+		assert_eq!(wide.arr.t(), tall.arr);
+
+		// A grid and its transpose flash in the same way
+		let (mut wide, mut tall) = (wide, tall);
+		for _ in 0..100 {
+			assert_eq!(
+				wide.tick_in_place_and_count_flashes(),
+				tall.tick_in_place_and_count_flashes()
+			);
+			assert_eq!(wide.arr.t(), tall.arr);
+		}
+
+		let mut row = Octopi::parse("99899").unwrap();
+		assert_eq!(row.tick_in_place_and_count_flashes(), 5);
+		assert_eq!(row.arr, array![[0, 0, 0, 0, 0]]);
+	}
+
+	#[test]
+	fn test_history() {
+		let mut octopi = Octopi::parse("11111\n19991\n19191\n19991\n11111").unwrap();
+		let flashes = octopi.tick_in_place();
+		assert_eq!(
+			flashes.waves,
+			vec![
+				vec![
+					[1, 1],
+					[1, 2],
+					[1, 3],
+					[2, 1],
+					[2, 3],
+					[3, 1],
+					[3, 2],
+					[3, 3]
+				],
+				vec![[2, 2]],
+			]
+		);
+		assert_eq!(
+			octopi.arr,
+			array![
+				[3, 4, 5, 4, 3],
+				[4, 0, 0, 0, 4],
+				[5, 0, 0, 0, 5],
+				[4, 0, 0, 0, 4],
+				[3, 4, 5, 4, 3]
+			]
+		);
+		assert_eq!(octopi.tick_in_place(), StepFlashes::default());
+
+		let octopi = Octopi::parse(include_str!("sample_input.txt")).unwrap();
+		assert_eq!(pt2(octopi.clone()), Some(195));
+		let history = octopi.run_until_cycle();
+		assert_eq!(history.first_sync, Some(195));
+		assert_eq!((history.cycle_start, history.period), (195, 10));
+		assert_eq!(
+			history.steps[..100]
+				.iter()
+				.map(StepFlashes::n_flashes)
+				.sum::<usize>(),
+			1656
+		);
+
+		// These never synchronize; one cycles from the start, the other only after a few steps
+		for (input, cycle_start, period) in [("29", 0, 9), ("360\n837", 4, 35)] {
+			let octopi = Octopi::parse(input).unwrap();
+			let history = octopi.clone().run_until_cycle();
+			assert_eq!(history.first_sync, None);
+			assert_eq!(pt2(octopi.clone()), None);
+			assert_eq!((history.cycle_start, history.period), (cycle_start, period));
+
+			let mut octopi = octopi;
+			for _ in 0..cycle_start {
+				octopi.tick_in_place();
+			}
+			let start = octopi.arr.clone();
+			for _ in 0..period {
+				octopi.tick_in_place();
+			}
+			assert_eq!(octopi.arr, start);
+		}
+
+		// Octopi that start out all at 0 are synchronized after 0 steps
+		let zeros = Octopi::parse("000\n000").unwrap();
+		assert_eq!(pt2(zeros.clone()), Some(0));
+		let history = zeros.run_until_cycle();
+		assert_eq!(
+			(history.first_sync, history.cycle_start, history.period),
+			(Some(0), 0, 10)
+		);
+
+		let mut rng = crate::utils::SplitMix64::new(11);
+		for _ in 0..200 {
+			let grid = (0..2)
+				.map(|_| {
+					(0..3)
+						.map(|_| rng.below(10).to_string())
+						.collect::<String>()
+				})
+				.collect::<Vec<_>>()
+				.join("\n");
+			let octopi = Octopi::parse(&grid).unwrap();
+			assert_eq!(
+				pt2(octopi.clone()),
+				octopi.run_until_cycle().first_sync,
+				"{grid}"
+			);
+		}
+	}
 }
@@ -43,4 +43,43 @@ pub(crate) fn abs_diff(a: usize, b: usize) -> usize {
 		b - a
 	}
 }
+
+/// A small, fast, seedable PRNG (Steele, Lea, and Flood's `SplitMix64`); not suitable for
+/// cryptography
+#[derive(Debug, Clone)]
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+	const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+	pub(crate) fn new(seed: u64) -> Self {
+		Self(seed)
+	}
+
+	/// A generator for the `idx`-th of many independent streams derived from `seed`
+	pub(crate) fn for_stream(seed: u64, idx: u64) -> Self {
+		Self::new(Self::new(seed.wrapping_add(idx.wrapping_mul(Self::GAMMA))).next_u64())
+	}
+
+	pub(crate) fn next_u64(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(Self::GAMMA);
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	/// A number in `0..n`
+	pub(crate) fn below(&mut self, n: usize) -> usize {
+		let n = n as u64;
+		usize::try_from((u128::from(self.next_u64()) * u128::from(n)) >> 64).unwrap()
+	}
+
+	pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+		for i in (1..items.len()).rev() {
+			let j = self.below(i + 1);
+			items.swap(i, j);
+		}
+	}
+}
 // end::code[]